@@ -0,0 +1,55 @@
+// Compares extract_all_sequential with extract_all_prefetch on a generated
+// archive; run with `cargo bench`.
+use std::fs::{self, File};
+use std::time::{Duration, Instant};
+
+use adat_format::{Package, PackageWriter};
+
+const ENTRIES: usize = 200;
+const ENTRY_SIZE: usize = 256 * 1024;
+const RUNS: u32 = 5;
+
+fn entry_data(seed: usize) -> Vec<u8> {
+    // compressible, but not trivially so
+    (0..ENTRY_SIZE).map(|i| ((i * 31 + seed) % 97) as u8 ^ (i / 4096) as u8).collect()
+}
+
+fn time(runs: u32, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        f();
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    let root = std::env::temp_dir().join(format!("adat-format-bench-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    let archive_path = root.join("archive.dat");
+
+    let mut writer = PackageWriter::new(File::create(&archive_path).unwrap());
+    writer.set_compression_level(6);
+    for idx in 0..ENTRIES {
+        writer.add_entry(&format!("dir{}/file{}.bin", idx % 8, idx), &entry_data(idx)).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let mut file = File::open(&archive_path).unwrap();
+    let mut package = Package::mount_from_cursor(&mut file).unwrap();
+    let dest = root.join("out");
+
+    let sequential = time(RUNS, || {
+        package.extract_all_sequential(&dest).unwrap();
+    });
+    println!("extract_all_sequential      {:>10.2?}", sequential);
+
+    for depth in [1, 4, 16] {
+        let prefetch = time(RUNS, || {
+            package.extract_all_prefetch(&dest, depth).unwrap();
+        });
+        println!("extract_all_prefetch({:>2})    {:>10.2?}", depth, prefetch);
+    }
+
+    drop(package);
+    fs::remove_dir_all(&root).unwrap();
+}
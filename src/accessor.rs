@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::{Result, Package, Toc};
+use crate::{text_from_bytes, text_from_bytes_lossy};
+
+/// A handle for reading entries of a mounted archive file, independent of
+/// the `Package` it came from.
+///
+/// It shares the `Toc` with the package and reads through its own clone of
+/// the file handle. Reads are positional and leave the file offset alone,
+/// so they take `&self` and any number of threads can read from one handle,
+/// or from several, at the same time. Dereferences to the archive's `Toc`.
+#[derive(Debug)]
+pub struct EntryAccessor {
+    file: File,
+    toc: Arc<Toc>,
+    max_decompressed_size: Option<usize>
+}
+
+// reads at an offset of its own, so concurrent readers of one file never race on the shared one
+struct PositionalReader<'a> {
+    file: &'a File,
+    position: u64
+}
+
+impl Read for PositionalReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        let read = std::os::unix::fs::FileExt::read_at(self.file, buf, self.position)?;
+        #[cfg(windows)]
+        let read = std::os::windows::fs::FileExt::seek_read(self.file, buf, self.position)?;
+
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for PositionalReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.file.metadata()?.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta)
+        };
+
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+        Ok(self.position)
+    }
+}
+
+impl Package<'_, File> {
+    /// Clones the file handle into an `EntryAccessor` sharing this package's
+    /// metadata and decompressed size limit.
+    pub fn reader_handle(&self) -> Result<EntryAccessor> {
+        Ok(EntryAccessor {
+            file: self.cursor.get_ref().try_clone()?,
+            toc: self.toc.clone(),
+            max_decompressed_size: self.max_decompressed_size
+        })
+    }
+}
+
+impl EntryAccessor {
+    pub fn try_clone(&self) -> Result<Self> {
+        Ok(EntryAccessor {
+            file: self.file.try_clone()?,
+            toc: self.toc.clone(),
+            max_decompressed_size: self.max_decompressed_size
+        })
+    }
+
+    /// Like `Package::set_max_decompressed_size`, for reads through this handle only.
+    pub fn set_max_decompressed_size(&mut self, limit: usize) {
+        self.max_decompressed_size = Some(limit);
+    }
+
+    pub fn max_decompressed_size(&self) -> Option<usize> {
+        self.max_decompressed_size
+    }
+
+    fn reader(&self) -> PositionalReader<'_> {
+        PositionalReader { file: &self.file, position: 0 }
+    }
+
+    pub fn read_entry(&self, entry_path: &str) -> Result<Vec<u8>> {
+        let pe = self.toc.readable_entry(entry_path, self.max_decompressed_size)?;
+        pe.read_entry(&mut self.reader(), &*self.toc.codec)
+    }
+
+    /// Reads the compressed bytes of an entry exactly as stored, without inflating them.
+    pub fn read_entry_raw(&self, entry_path: &str) -> Result<Vec<u8>> {
+        let pe = self.toc.lookup(entry_path)?;
+        pe.read_entry_raw(&mut self.reader())
+    }
+
+    pub fn read_text_entry(&self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).and_then(text_from_bytes)
+    }
+
+    /// Like `read_text_entry`, but replaces invalid UTF-8 sequences with U+FFFD.
+    pub fn read_text_entry_lossy(&self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).map(text_from_bytes_lossy)
+    }
+
+    /// The metadata shared with the package and every other handle.
+    pub fn toc(&self) -> &Arc<Toc> {
+        &self.toc
+    }
+}
+
+impl Deref for EntryAccessor {
+    type Target = Toc;
+
+    fn deref(&self) -> &Toc {
+        &self.toc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AdatError, OwnedPackage, PackageWriter};
+    use std::fs::File;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn reads_concurrently() {
+        let path = std::env::temp_dir().join(format!("adat-accessor-{}.dat", std::process::id()));
+        let mut writer = PackageWriter::new(File::create(&path).unwrap());
+        for i in 0..8u8 {
+            writer.add_entry(&format!("entry{}.bin", i), &vec![i; 20_000 + i as usize]).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let package = OwnedPackage::mount_from_path(&path).unwrap();
+        let shared = package.reader_handle().unwrap();
+        assert!(Arc::ptr_eq(shared.toc(), package.reader_handle().unwrap().toc()));
+
+        thread::scope(|scope| {
+            for i in 0..8u8 {
+                let own = shared.try_clone().unwrap();
+                let shared = &shared;
+                scope.spawn(move || {
+                    let name = format!("entry{}.bin", i);
+                    for _ in 0..10 {
+                        assert_eq!(own.read_entry(&name).unwrap(), vec![i; 20_000 + i as usize]);
+                        assert_eq!(shared.read_entry(&name).unwrap().len(), 20_000 + i as usize);
+                    }
+                });
+            }
+        });
+
+        let mut limited = package.reader_handle().unwrap();
+        limited.set_max_decompressed_size(1000);
+        assert!(matches!(limited.read_entry("entry0.bin"), Err(AdatError::EntryTooLarge { .. })));
+        assert!(matches!(limited.read_entry("missing"), Err(AdatError::EntryNotFound(_))));
+
+        drop(package);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::SeekFrom;
+use std::ops::Deref;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::{AdatError, Result, MountOptions, PackageHeader, PackageEntry, Toc};
+use crate::{decode_entry, text_from_bytes, text_from_bytes_lossy};
+use crate::ADAT_HEADER_SIZE;
+
+/// A mounted archive read through a Tokio `AsyncRead + AsyncSeek` reader.
+///
+/// Seeks and reads are awaited; decompression runs on the blocking thread
+/// pool, so a large entry does not stall the runtime. Dereferences to the
+/// archive's `Toc`, like `Package`.
+#[derive(Debug)]
+pub struct AsyncPackage<R: AsyncRead + AsyncSeek + Unpin> {
+    reader: R,
+    toc: Toc,
+    max_decompressed_size: Option<usize>
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncPackage<R> {
+    pub async fn mount(reader: R) -> Result<Self> {
+        AsyncPackage::mount_with_options(reader, &MountOptions::default()).await
+    }
+
+    pub async fn mount_with_options(mut reader: R, options: &MountOptions) -> Result<Self> {
+        let file_len = reader.seek(SeekFrom::End(0)).await?;
+        reader.seek(SeekFrom::Start(0)).await?;
+
+        let mut buffer: [u8; ADAT_HEADER_SIZE as usize] = [0; ADAT_HEADER_SIZE as usize];
+        reader.read_exact(&mut buffer).await?;
+        let header = PackageHeader::read_package_header(&mut &buffer[..], &options.format)?;
+        let entry_count = Toc::check_header(&header, file_len, options)?;
+
+        let mut toc_data: Vec<u8> = vec![0; header.toc_length as usize];
+        reader.seek(SeekFrom::Start(header.toc_offset as u64)).await?;
+        reader.read_exact(&mut toc_data).await?;
+        let entries = PackageEntry::read_package_entries(&mut &toc_data[..], entry_count)?;
+
+        Ok(AsyncPackage {
+            reader,
+            toc: Toc::from_entries(header, entries, file_len, options, false)?.0,
+            max_decompressed_size: options.limits.max_decompressed_size
+        })
+    }
+
+    /// Like `Package::set_max_decompressed_size`.
+    pub fn set_max_decompressed_size(&mut self, limit: usize) {
+        self.max_decompressed_size = Some(limit);
+    }
+
+    pub fn max_decompressed_size(&self) -> Option<usize> {
+        self.max_decompressed_size
+    }
+
+    /// Reads the compressed bytes of an entry exactly as stored, without inflating them.
+    pub async fn read_entry_raw(&mut self, entry_path: &str) -> Result<Vec<u8>> {
+        let pe = self.toc.lookup(entry_path)?;
+        let mut compressed_data: Vec<u8> = vec![0; pe.compressed_length];
+
+        let offset = pe.offset as u64;
+        let name = pe.path.clone();
+        let read = async {
+            self.reader.seek(SeekFrom::Start(offset)).await?;
+            self.reader.read_exact(&mut compressed_data).await
+        };
+        read.await.map_err(|e| AdatError::from(e).with_entry(&name))?;
+
+        Ok(compressed_data)
+    }
+
+    pub async fn read_entry(&mut self, entry_path: &str) -> Result<Vec<u8>> {
+        let pe = self.toc.readable_entry(entry_path, self.max_decompressed_size)?;
+        let length = pe.length;
+        let name = pe.path.clone();
+
+        let compressed_data = self.read_entry_raw(entry_path).await?;
+        let codec = self.toc.codec.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut data: Vec<u8> = Vec::new();
+            decode_entry(&*codec, &compressed_data, length, &mut data).map(|_| data).map_err(|e| e.with_entry(&name))
+        }).await.map_err(|e| AdatError::Io(std::io::Error::other(e)))?
+    }
+
+    pub async fn read_text_entry(&mut self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).await.and_then(text_from_bytes)
+    }
+
+    /// Like `read_text_entry`, but replaces invalid UTF-8 sequences with U+FFFD.
+    pub async fn read_text_entry_lossy(&mut self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).await.map(text_from_bytes_lossy)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> Deref for AsyncPackage<R> {
+    type Target = Toc;
+
+    fn deref(&self) -> &Toc {
+        &self.toc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PackageWriter;
+    use std::io::Cursor;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn reads_asynchronously() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a.txt", b"async text").unwrap();
+        writer.add_entry("b.bin", &[9; 50_000]).unwrap();
+        let cursor = writer.finalize().unwrap();
+
+        block_on(async {
+            let mut package = AsyncPackage::mount(cursor).await.unwrap();
+            assert_eq!(package.len(), 2);
+            assert_eq!(package.read_text_entry("a.txt").await.unwrap(), "async text");
+            assert_eq!(package.read_entry("b.bin").await.unwrap(), vec![9; 50_000]);
+            assert!(matches!(package.read_entry("c").await, Err(AdatError::EntryNotFound(_))));
+
+            package.set_max_decompressed_size(1000);
+            assert!(matches!(package.read_entry("b.bin").await, Err(AdatError::EntryTooLarge { .. })));
+        });
+
+        let not_adat = Cursor::new(b"ZDAT\x10\0\0\0\0\0\0\0\x09\0\0\0".to_vec());
+        assert!(matches!(block_on(AsyncPackage::mount(not_adat)), Err(AdatError::MagicMismatch { .. })));
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::io::{Read, Seek, Write};
+
+use crate::{AdatError, Result, PackageWriter};
+use crate::writer::DEFAULT_COMPRESSION_LEVEL;
+
+// where the contents of an entry come from; readers are only read by build
+enum EntrySource<'a> {
+    Bytes(&'a [u8]),
+    Reader(Box<dyn Read + 'a>)
+}
+
+impl fmt::Debug for EntrySource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntrySource::Bytes(data) => write!(f, "Bytes({} bytes)", data.len()),
+            EntrySource::Reader(_) => write!(f, "Reader")
+        }
+    }
+}
+
+/// Describes an archive by chaining calls, then writes it with `build`, as in
+/// `PackageWriter::builder(out).add_bytes("a.txt", b"hi").compression_level(9).build()?`.
+///
+/// Nothing is written before `build`, which adds the entries in the order
+/// they were given through a `PackageWriter` and finalizes it. Errors, such
+/// as an invalid or duplicate name or a failing reader, surface from
+/// `build` as well.
+#[derive(Debug)]
+pub struct PackageBuilder<'a, W: Write + Seek> {
+    out: W,
+    entries: Vec<(String, EntrySource<'a>)>,
+    compression_level: u8
+}
+
+impl<'a, W: Write + Seek> PackageBuilder<'a, W> {
+    pub fn new(out: W) -> Self {
+        PackageBuilder { out, entries: Vec::new(), compression_level: DEFAULT_COMPRESSION_LEVEL }
+    }
+
+    pub fn add_bytes(mut self, name: &str, data: &'a [u8]) -> Self {
+        self.entries.push((name.to_string(), EntrySource::Bytes(data)));
+        self
+    }
+
+    /// Adds an entry read to its end by `build`.
+    pub fn add_reader(mut self, name: &str, reader: impl Read + 'a) -> Self {
+        self.entries.push((name.to_string(), EntrySource::Reader(Box::new(reader))));
+        self
+    }
+
+    /// zlib level for all entries, as in `PackageWriter::set_compression_level`.
+    pub fn compression_level(mut self, level: u8) -> Self {
+        self.compression_level = level;
+        self
+    }
+
+    /// Writes the archive and returns the output.
+    pub fn build(self) -> Result<W> {
+        let mut writer = PackageWriter::new(self.out);
+        writer.set_compression_level(self.compression_level);
+        let mut buffer: Vec<u8> = Vec::new();
+
+        for (name, source) in self.entries {
+            match source {
+                EntrySource::Bytes(data) => writer.add_entry(&name, data)?,
+                EntrySource::Reader(mut reader) => {
+                    buffer.clear();
+                    reader.read_to_end(&mut buffer).map_err(|e| AdatError::from(e).with_entry(&name))?;
+                    writer.add_entry(&name, &buffer)?;
+                }
+            }
+        }
+
+        writer.finalize()
+    }
+}
+
+impl<W: Write + Seek> PackageWriter<W> {
+    /// Starts a `PackageBuilder` writing to `out`.
+    pub fn builder<'a>(out: W) -> PackageBuilder<'a, W> {
+        PackageBuilder::new(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Package;
+    use std::io::{self, Cursor};
+
+    #[test]
+    fn builds_by_chaining() {
+        let big: Vec<u8> = (0..100_000u32).map(|i| (i % 7) as u8).collect();
+        let mut cursor = PackageWriter::builder(Cursor::new(Vec::new()))
+            .add_bytes("a.txt", b"hi")
+            .add_reader("big.bin", big.as_slice())
+            .add_reader("dir/empty", io::empty())
+            .compression_level(9)
+            .build()
+            .unwrap();
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["a.txt", "big.bin", "dir/empty"]);
+        assert_eq!(package.read_text_entry("a.txt").unwrap(), "hi");
+        assert_eq!(package.read_entry("big.bin").unwrap(), big);
+        assert!(package.read_entry("dir/empty").unwrap().is_empty());
+
+        let duplicate = PackageBuilder::new(Cursor::new(Vec::new())).add_bytes("a", b"1").add_bytes("a", b"2").build();
+        assert!(matches!(duplicate, Err(AdatError::DuplicateEntry(_))));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{Seek, Read};
+use std::sync::Arc;
+
+use crate::{Result, Package};
+
+// decompressed entries by name, evicted oldest first once over capacity
+#[derive(Debug, Default)]
+pub(crate) struct EntryCache {
+    entries: HashMap<String, Arc<[u8]>>,
+    order: VecDeque<String>, // insertion order of the names in entries
+    size: usize, // total bytes held
+    capacity: Option<usize>
+}
+
+impl EntryCache {
+    fn insert(&mut self, name: &str, data: Arc<[u8]>) {
+        if self.capacity.is_some_and(|capacity| data.len() > capacity) {
+            return;
+        }
+
+        self.size += data.len();
+        self.entries.insert(name.to_string(), data);
+        self.order.push_back(name.to_string());
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.capacity.is_some_and(|capacity| self.size > capacity) {
+            let Some(oldest) = self.order.pop_front() else { break };
+            if let Some(data) = self.entries.remove(&oldest) {
+                self.size -= data.len();
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.size = 0;
+    }
+}
+
+impl<T: Read + Seek> Package<'_, T> {
+    /// Like `read_entry`, but keeps the decompressed data around, so reading
+    /// the same entry again is only a lookup.
+    pub fn read_entry_cached(&mut self, entry_path: &str) -> Result<Arc<[u8]>> {
+        // keyed by the stored name, so every way of spelling it shares one copy
+        let pe = self.toc.readable_entry(entry_path, self.max_decompressed_size)?;
+        let name = pe.path.clone();
+        if let Some(data) = self.cache.entries.get(&name) {
+            return Ok(data.clone());
+        }
+
+        let data: Arc<[u8]> = self.read_entry(&name)?.into();
+        self.cache.insert(&name, data.clone());
+        Ok(data)
+    }
+
+    /// Caps the total size of cached entries, evicting the oldest ones as
+    /// needed; entries larger than the cap are never cached.
+    pub fn set_cache_capacity(&mut self, bytes: usize) {
+        self.cache.capacity = Some(bytes);
+        self.cache.evict();
+    }
+
+    /// Total size of the entries currently cached.
+    pub fn cache_size(&self) -> usize {
+        self.cache.size
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{package_with, AdatError, Package};
+    use std::sync::Arc;
+
+    #[test]
+    fn caches_entries() {
+        let mut cursor = package_with(&[("a.bin", &[1; 100]), ("b.bin", &[2; 200]), ("c.bin", &[3; 300])]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let first = package.read_entry_cached("a.bin").unwrap();
+        assert_eq!(&first[..], &[1; 100]);
+        assert!(Arc::ptr_eq(&first, &package.read_entry_cached("a.bin").unwrap()));
+        assert_eq!(package.cache_size(), 100);
+
+        package.read_entry_cached("b.bin").unwrap();
+        package.set_cache_capacity(550);
+        package.read_entry_cached("c.bin").unwrap();
+        // a.bin was evicted to make room
+        assert_eq!(package.cache_size(), 500);
+        assert!(!Arc::ptr_eq(&first, &package.read_entry_cached("a.bin").unwrap()));
+
+        package.set_cache_capacity(50);
+        assert_eq!(package.cache_size(), 0);
+        package.read_entry_cached("c.bin").unwrap();
+        assert_eq!(package.cache_size(), 0);
+
+        package.set_cache_capacity(1000);
+        package.read_entry_cached("c.bin").unwrap();
+        package.clear_cache();
+        assert_eq!(package.cache_size(), 0);
+    }
+
+    #[test]
+    fn shrinking_capacity_evicts_oldest_first() {
+        let mut cursor = package_with(&[("a.bin", &[1; 100]), ("b.bin", &[2; 200]), ("c.bin", &[3; 300])]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let a = package.read_entry_cached("a.bin").unwrap();
+        let b = package.read_entry_cached("b.bin").unwrap();
+        let c = package.read_entry_cached("c.bin").unwrap();
+        assert_eq!(package.cache_size(), 600);
+
+        // a.bin and b.bin were cached first, so they go, though c.bin alone would do
+        package.set_cache_capacity(300);
+        assert_eq!(package.cache_size(), 300);
+        assert!(Arc::ptr_eq(&c, &package.read_entry_cached("c.bin").unwrap()));
+        assert!(!Arc::ptr_eq(&b, &package.read_entry_cached("b.bin").unwrap()));
+        assert!(!Arc::ptr_eq(&a, &package.read_entry_cached("a.bin").unwrap()));
+    }
+
+    #[test]
+    fn cached_entries_respect_size_limit() {
+        let mut cursor = package_with(&[("big.bin", &[1; 1000])]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        package.read_entry_cached("big.bin").unwrap();
+        package.set_max_decompressed_size(500);
+        assert!(matches!(package.read_entry_cached("big.bin"), Err(AdatError::EntryTooLarge { .. })));
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use std::io;
+
+use miniz_oxide::inflate::{DecompressError, TINFLStatus};
+use miniz_oxide::inflate::core::{decompress, DecompressorOxide};
+use miniz_oxide::inflate::core::inflate_flags::{TINFL_FLAG_PARSE_ZLIB_HEADER, TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF};
+
+use crate::{AdatError, DecompressErrorKind, Result};
+
+/// Decompresses entry payloads; set one with `MountOptions::codec`.
+///
+/// `expected_len` is the decompressed length the TOC declares for the entry,
+/// a codec should not produce more than that.
+pub trait Codec: fmt::Debug + Send + Sync {
+    fn decompress(&self, input: &[u8], expected_len: usize) -> io::Result<Vec<u8>>;
+
+    /// Like `decompress`, but writes into `out`, which codecs may reuse the allocation of.
+    fn decompress_into(&self, input: &[u8], expected_len: usize, out: &mut Vec<u8>) -> io::Result<usize> {
+        *out = self.decompress(input, expected_len)?;
+        Ok(out.len())
+    }
+
+    /// Whether the payloads are zlib or raw deflate streams, which lets
+    /// `open_entry` inflate them incrementally instead of all at once.
+    fn is_deflate(&self) -> bool {
+        false
+    }
+}
+
+/// The codec of stock ADAT archives: zlib, or raw deflate if the zlib header is missing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZlibCodec;
+
+impl Codec for ZlibCodec {
+    fn decompress(&self, input: &[u8], expected_len: usize) -> io::Result<Vec<u8>> {
+        let mut out: Vec<u8> = Vec::new();
+        inflate_into(input, expected_len, &mut out)?;
+        Ok(out)
+    }
+
+    fn decompress_into(&self, input: &[u8], expected_len: usize, out: &mut Vec<u8>) -> io::Result<usize> {
+        Ok(inflate_into(input, expected_len, out)?)
+    }
+
+    fn is_deflate(&self) -> bool {
+        true
+    }
+}
+
+/// Zstandard payloads; entries without a zstd frame header are left to `ZlibCodec`,
+/// so archives mixing both decompress with this codec set for the whole package.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZstdCodec;
+
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[cfg(feature = "zstd")]
+impl Codec for ZstdCodec {
+    fn decompress(&self, input: &[u8], expected_len: usize) -> io::Result<Vec<u8>> {
+        if input.starts_with(&ZSTD_MAGIC) {
+            zstd::bulk::decompress(input, expected_len)
+        } else {
+            ZlibCodec.decompress(input, expected_len)
+        }
+    }
+}
+
+// stored entries are recognised by a compressed length equal to the decompressed
+// one and copied as they are; everything else goes through the codec and has to
+// come out at exactly the declared length
+pub(crate) fn decode_entry(codec: &dyn Codec, input: &[u8], length: usize, out: &mut Vec<u8>) -> Result<usize> {
+    if input.len() == length {
+        out.clear();
+        out.extend_from_slice(input);
+        return Ok(length);
+    }
+
+    let got = codec.decompress_into(input, length, out).map_err(codec_error)?;
+    if got != length {
+        return Err(AdatError::LengthMismatch { expected: length, got });
+    }
+    Ok(got)
+}
+
+// errors of the built-in codecs come back as they were, anything else is a decompression failure
+pub(crate) fn codec_error(e: io::Error) -> AdatError {
+    AdatError::unwrap_io(e).unwrap_or_else(|e| {
+        AdatError::Decompress { kind: DecompressErrorKind::Codec, message: e.to_string() }
+    })
+}
+
+// some producers store raw deflate streams; a zlib stream starts with a CMF/FLG pair
+// declaring deflate that is a multiple of 31
+pub(crate) fn has_zlib_header(input: &[u8]) -> bool {
+    input.len() >= 2 && input[0] & 0x0f == 8 && u16::from_be_bytes([input[0], input[1]]).is_multiple_of(31)
+}
+
+pub(crate) fn stream_kind(zlib: bool) -> &'static str {
+    if zlib { "zlib" } else { "raw deflate" }
+}
+
+// decompresses input into out, reusing its allocation, growing it up to limit bytes
+pub(crate) fn inflate_into(mut input: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<usize> {
+    let zlib = has_zlib_header(input);
+    let flags = if zlib {
+        TINFL_FLAG_PARSE_ZLIB_HEADER | TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF
+    } else {
+        TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF
+    };
+    let mut decompressor = Box::<DecompressorOxide>::default();
+    let mut out_pos = 0;
+
+    let initial_len = input.len().saturating_mul(2).max(out.capacity()).min(limit);
+    out.clear();
+    out.resize(initial_len, 0);
+
+    loop {
+        let (status, in_consumed, out_consumed) = decompress(&mut decompressor, input, out, out_pos, flags);
+        out_pos += out_consumed;
+
+        match status {
+            TINFLStatus::Done => {
+                out.truncate(out_pos);
+                return Ok(out_pos);
+            }
+            TINFLStatus::HasMoreOutput if out.len() < limit => {
+                input = &input[in_consumed..];
+                let new_len = out.len().saturating_mul(2).max(1).min(limit);
+                out.resize(new_len, 0);
+            }
+            status => {
+                out.truncate(out_pos);
+                let kind = match status {
+                    TINFLStatus::HasMoreOutput => DecompressErrorKind::OutputLimit,
+                    TINFLStatus::NeedsMoreInput | TINFLStatus::FailedCannotMakeProgress => DecompressErrorKind::Truncated,
+                    _ => DecompressErrorKind::InvalidData
+                };
+                let message = format!("{} ({} stream)", DecompressError { status, output: Vec::new() }, stream_kind(zlib));
+                return Err(AdatError::Decompress { kind, message });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MountOptions, Package, PackageWriter};
+    use std::io::{Cursor, Read};
+
+    // zlib, but the payload is stored back to front
+    #[derive(Debug)]
+    struct ReversedCodec;
+
+    impl Codec for ReversedCodec {
+        fn decompress(&self, input: &[u8], expected_len: usize) -> io::Result<Vec<u8>> {
+            let mut data = ZlibCodec.decompress(input, expected_len)?;
+            data.reverse();
+            Ok(data)
+        }
+    }
+
+    #[test]
+    fn decompresses_with_registered_codec() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a.txt", "olleh ".repeat(20).as_bytes()).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let expected = " hello".repeat(20).into_bytes();
+
+        let options = MountOptions::new().codec(ReversedCodec);
+        let mut package = Package::mount_with_options(&mut cursor, &options).unwrap();
+        assert_eq!(package.read_entry("a.txt").unwrap(), expected);
+
+        let mut streamed = Vec::new();
+        let mut reader = package.open_entry("a.txt").unwrap();
+        assert_eq!(reader.len(), 120);
+        reader.read_to_end(&mut streamed).unwrap();
+        assert_eq!(streamed, expected);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn round_trips_zstd() {
+        use crate::{PackageEntry, PackageHeader, ADAT_HEADER_SIZE, ADAT_ENTRY_SIZE, ADAT_MAGIC, ADAT_VERSION};
+
+        let text = b"zstd compressed entry, zstd compressed entry";
+        let compressed = zstd::bulk::compress(text, 3).unwrap();
+
+        let mut name: [u8; 128] = [0; 128];
+        name[..5].copy_from_slice(b"z.txt");
+        let entry = PackageEntry {
+            path: "z.txt".to_string(),
+            name,
+            offset: ADAT_HEADER_SIZE,
+            length: text.len(),
+            compressed_length: compressed.len(),
+            u0: 0
+        };
+        let header = PackageHeader {
+            magic: u32::from_le_bytes(ADAT_MAGIC),
+            toc_offset: ADAT_HEADER_SIZE + compressed.len() as u32,
+            toc_length: ADAT_ENTRY_SIZE,
+            version: ADAT_VERSION
+        };
+
+        let mut data = Vec::new();
+        header.write_package_header(&mut data).unwrap();
+        data.extend_from_slice(&compressed);
+        entry.write_package_entry(&mut data).unwrap();
+        let mut cursor = Cursor::new(data);
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let error = package.read_entry("z.txt").unwrap_err();
+        assert!(matches!(error.without_context(), AdatError::Decompress { kind: DecompressErrorKind::InvalidData, .. }));
+        assert!(error.to_string().starts_with("failed to read entry 'z.txt': "));
+        drop(package);
+
+        let options = MountOptions::new().codec(ZstdCodec);
+        let mut package = Package::mount_with_options(&mut cursor, &options).unwrap();
+        assert_eq!(package.read_entry("z.txt").unwrap(), text);
+
+        // zlib entries still read through the zstd codec
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("plain.txt", b"zlib").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_with_options(&mut cursor, &options).unwrap();
+        assert_eq!(package.read_entry("plain.txt").unwrap(), b"zlib");
+    }
+
+    #[test]
+    fn reports_decompress_error_kinds() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a.bin", &[1; 10_000]).unwrap();
+        let mut data = writer.finalize().unwrap().into_inner();
+        let length_at = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize + 128 + 4;
+        data[length_at..length_at + 4].copy_from_slice(&5000u32.to_le_bytes()); // under-declared
+
+        let mut cursor = Cursor::new(data);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let error = package.read_entry("a.bin").unwrap_err();
+        assert!(matches!(error.without_context(), AdatError::Decompress { kind: DecompressErrorKind::OutputLimit, .. }));
+        drop(package);
+
+        cursor.get_mut()[length_at..length_at + 4].copy_from_slice(&10_001u32.to_le_bytes()); // over-declared
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let error = package.read_entry("a.bin").unwrap_err();
+        assert!(matches!(error.without_context(), AdatError::LengthMismatch { expected: 10_001, got: 10_000 }));
+        assert!(matches!(&package.verify().unwrap_err()[0].1, AdatError::WithEntry { source, .. } if matches!(**source, AdatError::LengthMismatch { .. })));
+    }
+
+    #[test]
+    fn keeps_builtin_errors() {
+        let broken = AdatError::Decompress { kind: DecompressErrorKind::Truncated, message: "broken".to_string() };
+        let error = codec_error(io::Error::from(broken));
+        assert!(matches!(error, AdatError::Decompress { kind: DecompressErrorKind::Truncated, message } if message == "broken"));
+
+        let error = codec_error(io::Error::other("foreign codec failure"));
+        assert!(matches!(error, AdatError::Decompress { kind: DecompressErrorKind::Codec, message } if message == "foreign codec failure"));
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{Seek, Read};
+
+use crate::{Result, Package, Toc};
+
+/// Entry-level differences between two archives, see `Toc::diff`.
+///
+/// All lists are sorted by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackageDiff {
+    pub added: Vec<String>, // only in the other archive
+    pub removed: Vec<String>, // only in this one
+    pub modified: Vec<String> // in both, but different
+}
+
+impl PackageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl Toc {
+    // added and removed names, and the names in both for which changed returns true,
+    // given the positions of the entry in self and in other
+    fn diff_with<F: FnMut(usize, usize) -> Result<bool>>(&self, other: &Toc, mut changed: F) -> Result<PackageDiff> {
+        let mut diff = PackageDiff::default();
+
+        for name in self.collect_names_sorted() {
+            match other.exact_position(name) {
+                None => diff.removed.push(name.to_string()),
+                Some(new) => {
+                    let old = self.exact_position(name).expect("listed names are indexed");
+                    if changed(old, new)? {
+                        diff.modified.push(name.to_string());
+                    }
+                }
+            }
+        }
+        diff.added = other.collect_names_sorted().into_iter().filter(|name| {
+            self.exact_position(name).is_none()
+        }).map(str::to_string).collect();
+
+        Ok(diff)
+    }
+
+    /// Compares the entries reachable by name in this archive, the old one,
+    /// with those in `other`, the new one. An entry counts as modified if
+    /// its decompressed length or its `u0` field differ; see
+    /// `Package::diff_contents` to compare the data itself.
+    pub fn diff(&self, other: &Toc) -> PackageDiff {
+        let diff = self.diff_with(other, |old, new| {
+            let (old, new) = (&self.entries[old], &other.entries[new]);
+            Ok(old.length != new.length || old.u0 != new.u0)
+        });
+        diff.expect("comparing metadata does not fail")
+    }
+}
+
+impl<T: Read + Seek> Package<'_, T> {
+    /// Like `diff`, but an entry counts as modified if its decompressed
+    /// contents differ. Entries whose lengths differ are not decompressed.
+    pub fn diff_contents<U: Read + Seek>(&mut self, other: &mut Package<'_, U>) -> Result<PackageDiff> {
+        let (old_toc, new_toc) = (self.toc.clone(), other.toc.clone());
+
+        old_toc.diff_with(&new_toc, |old, new| {
+            if old_toc.entries[old].length != new_toc.entries[new].length {
+                return Ok(true);
+            }
+            Ok(self.read_entry_by_index(old)? != other.read_entry_by_index(new)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package_with;
+
+    #[test]
+    fn diffs_entries() {
+        let mut old = package_with(&[("same.txt", b"same"), ("gone.txt", b"gone"), ("grown.txt", b"short"), ("edited.txt", b"abc")]);
+        let mut new = package_with(&[("same.txt", b"same"), ("grown.txt", b"longer"), ("edited.txt", b"xyz"), ("new.txt", b"new")]);
+        let mut old = Package::mount_from_cursor(&mut old).unwrap();
+        let mut new = Package::mount_from_cursor(&mut new).unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec!["new.txt"]);
+        assert_eq!(diff.removed, vec!["gone.txt"]);
+        assert_eq!(diff.modified, vec!["grown.txt"]);
+
+        let diff = old.diff_contents(&mut new).unwrap();
+        assert_eq!(diff.modified, vec!["edited.txt", "grown.txt"]);
+        assert_eq!(diff.added, vec!["new.txt"]);
+
+        assert!(old.diff(&old).is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::io::{Seek, Read, Write};
+
+use crate::{Result, Package, PackageWriter};
+use crate::writer::{encode_entry, DEFAULT_COMPRESSION_LEVEL};
+
+/// Removes entries from an archive by writing a compacted copy of it.
+///
+/// The source is only read from; `write_to` copies the compressed data of
+/// every remaining entry as is, packed one after the other, and builds a new
+/// TOC with the shifted offsets.
+#[derive(Debug)]
+pub struct PackageEditor<'p, 'b, T: Read + Seek> {
+    package: &'p mut Package<'b, T>,
+    removed: HashSet<String>
+}
+
+impl<'p, 'b, T: Read + Seek> PackageEditor<'p, 'b, T> {
+    pub fn new(package: &'p mut Package<'b, T>) -> Self {
+        PackageEditor {
+            package,
+            removed: HashSet::new()
+        }
+    }
+
+    /// Drops every TOC entry with the given name from the output.
+    pub fn remove(&mut self, entry_path: &str) -> Result<()> {
+        let name = self.package.lookup(entry_path)?.path.clone();
+        self.removed.insert(name);
+        Ok(())
+    }
+
+    pub fn is_removed(&self, entry_path: &str) -> bool {
+        self.removed.contains(entry_path)
+    }
+
+    /// Writes the archive without the removed entries to `out`.
+    ///
+    /// Removing every entry leaves a header-only archive, like `repack_to` of
+    /// an empty one; it mounts with `MountOptions::allow_empty`.
+    pub fn write_to<W: Write + Seek>(self, out: W) -> Result<W> {
+        let mut writer = PackageWriter::new(out);
+        let cursor = self.package.cursor.get_mut();
+
+        for pe in &self.package.toc.entries {
+            if self.removed.contains(&pe.path) {
+                continue;
+            }
+            let compressed_data = pe.read_entry_raw(cursor)?;
+            writer.add_raw_entry(pe, &compressed_data)?;
+        }
+
+        writer.finalize()
+    }
+}
+
+impl<T: Read + Seek> Package<'_, T> {
+    /// Writes a tightly packed copy of the archive to `out`: the entries
+    /// reachable by name, in TOC order, with their compressed data back to
+    /// back right after the header. Shadowed duplicates and any gaps are dropped.
+    ///
+    /// The compressed data is copied as is; see `repack_recompressed_to`.
+    pub fn repack_to<W: Write + Seek>(&mut self, out: W) -> Result<W> {
+        let mut writer = PackageWriter::new(out);
+        let cursor = self.cursor.get_mut();
+
+        for position in self.toc.reachable_positions() {
+            let pe = &self.toc.entries[position];
+            let compressed_data = pe.read_entry_raw(cursor)?;
+            writer.add_raw_entry(pe, &compressed_data)?;
+        }
+
+        writer.finalize()
+    }
+
+    /// Like `repack_to`, but decompresses every entry and compresses it
+    /// again with zlib at the best compression level; as with `add_entry`,
+    /// entries that would not get smaller are stored as they are.
+    pub fn repack_recompressed_to<W: Write + Seek>(&mut self, out: W) -> Result<W> {
+        let mut writer = PackageWriter::new(out);
+        let mut data: Vec<u8> = Vec::new();
+
+        for position in self.toc.reachable_positions() {
+            let (pe, codec, cursor) = self.readable_entry_at(position)?;
+            pe.read_entry_into(cursor, codec, &mut data)?;
+            writer.add_raw_entry(pe, &encode_entry(&data, DEFAULT_COMPRESSION_LEVEL))?;
+        }
+
+        writer.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{package_with, AdatError, MountOptions};
+    use std::io::Cursor;
+
+    #[test]
+    fn removes_middle_entry() {
+        let mut cursor = package_with(&[("first.txt", b"first"), ("middle.bin", &[8; 20_000]), ("last.txt", b"last")]);
+        let original_len = cursor.get_ref().len();
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let mut editor = PackageEditor::new(&mut package);
+        assert!(matches!(editor.remove("missing"), Err(AdatError::EntryNotFound(_))));
+        editor.remove("middle.bin").unwrap();
+        assert!(editor.is_removed("middle.bin"));
+        let mut compacted = editor.write_to(Cursor::new(Vec::new())).unwrap();
+        assert!(compacted.get_ref().len() < original_len);
+
+        let mut package = Package::mount_from_cursor(&mut compacted).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["first.txt", "last.txt"]);
+        assert_eq!(package.read_text_entry("first.txt").unwrap(), "first");
+        assert_eq!(package.read_text_entry("last.txt").unwrap(), "last");
+        assert!(package.verify().is_ok());
+    }
+
+    #[test]
+    fn removes_every_entry() {
+        let mut cursor = package_with(&[("a.txt", b"a"), ("b.txt", b"b")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let mut editor = PackageEditor::new(&mut package);
+        editor.remove("a.txt").unwrap();
+        editor.remove("b.txt").unwrap();
+        let mut emptied = editor.write_to(Cursor::new(Vec::new())).unwrap();
+        assert_eq!(emptied.get_ref().len(), 16);
+
+        assert!(matches!(Package::mount_from_cursor(&mut emptied), Err(AdatError::EmptyToc)));
+        let package = Package::mount_with_options(&mut emptied, &MountOptions::new().allow_empty(true)).unwrap();
+        assert!(package.is_empty());
+    }
+
+    #[test]
+    fn repacks_without_gaps() {
+        let mut writer = PackageWriter::append(package_with(&[("a.txt", b"first version"), ("b.bin", &[6; 10_000])])).unwrap();
+        writer.replace_existing(true);
+        writer.add_entry("a.txt", b"second version").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let mut repacked = package.repack_to(Cursor::new(Vec::new())).unwrap();
+        let mut recompressed = package.repack_recompressed_to(Cursor::new(Vec::new())).unwrap();
+
+        for out in [&mut repacked, &mut recompressed] {
+            let mut copy = Package::mount_from_cursor(out).unwrap();
+            assert_eq!(copy.entries_in_order().collect::<Vec<_>>(), vec!["b.bin", "a.txt"]);
+            assert_eq!(copy.read_text_entry("a.txt").unwrap(), "second version");
+            assert_eq!(copy.read_entry("b.bin").unwrap(), vec![6; 10_000]);
+
+            // data region is contiguous and the TOC follows it directly
+            let total = copy.total_compressed_size() as u32;
+            assert_eq!(copy.toc_offset(), 16 + total);
+        }
+        assert!(repacked.get_ref().len() < cursor.get_ref().len());
+    }
+
+    #[test]
+    fn recompresses_stored_entries_as_stored() {
+        // xorshift output, which zlib cannot shrink
+        let mut state = 0x2545_f491_u32;
+        let noise: Vec<u8> = (0..2000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        }).collect();
+        let mut cursor = package_with(&[("tiny.txt", b"abc"), ("noise.bin", &noise), ("zeros.bin", &[0; 5000])]);
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert!(package.entry_info("tiny.txt").unwrap().is_stored());
+        assert!(package.entry_info("noise.bin").unwrap().is_stored());
+        let mut recompressed = package.repack_recompressed_to(Cursor::new(Vec::new())).unwrap();
+
+        let mut copy = Package::mount_from_cursor(&mut recompressed).unwrap();
+        assert!(copy.entry_info("tiny.txt").unwrap().is_stored());
+        assert!(copy.entry_info("noise.bin").unwrap().is_stored());
+        assert!(!copy.entry_info("zeros.bin").unwrap().is_stored());
+        assert_eq!(copy.read_entry("tiny.txt").unwrap(), b"abc");
+        assert_eq!(copy.read_entry("noise.bin").unwrap(), noise);
+        assert_eq!(copy.read_entry("zeros.bin").unwrap(), vec![0; 5000]);
+        assert!(copy.verify().is_ok());
+    }
+}
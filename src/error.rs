@@ -0,0 +1,161 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::str::Utf8Error;
+
+/// Everything that can go wrong while reading or writing an ADAT archive.
+#[derive(Debug)]
+pub enum AdatError {
+    MagicMismatch { found: [u8; 4] },
+    UnsupportedVersion(u32),
+    EmptyToc,
+    TocMisaligned(u32), // toc_length is not a multiple of the entry size
+    TocOutOfBounds { toc_offset: u32, toc_length: u32, file_len: u64 },
+    EntryOutOfBounds { name: String, offset: u32, compressed_length: u32, file_len: u64 },
+    EntryOverlapsToc { name: String, offset: u32, compressed_length: u32, toc_offset: u32, toc_length: u32 },
+    EntryNotFound(String),
+    AmbiguousEntry { name: String, candidates: Vec<String> }, // case-insensitive lookup matched several names
+    IndexOutOfBounds { index: usize, len: usize },
+    TooManyEntries { count: u32, limit: u32 }, // the header declares more entries than MountLimits allow
+    NameOverLimit { name: String, length: usize, limit: usize }, // stored name longer than MountLimits allow
+    EntryTooLarge { name: String, length: u64, limit: u64 }, // declared length is over the configured limit
+    Decompress { kind: DecompressErrorKind, message: String },
+    LengthMismatch { expected: usize, got: usize }, // decompressed to fewer bytes than the declared length
+    InvalidName(Utf8Error), // entry name in the TOC is not UTF-8
+    InvalidText(Utf8Error), // entry payload read as text is not UTF-8
+    NameTooLong(String),
+    UnterminatedName(String), // the stored name fills its 128 bytes without a null byte, maybe truncated
+    InvalidEntryName(String), // empty or containing a null byte
+    DuplicateEntry(String),
+    UnsafeEntryPath(String), // would escape the extraction directory
+    SizeOverflow { what: &'static str, value: u64 }, // does not fit the 32 bit on-disk fields
+    WithEntry { name: String, source: Box<AdatError> }, // reading or decompressing the named entry failed
+    Io(std::io::Error)
+}
+
+pub type Result<T> = std::result::Result<T, AdatError>;
+
+/// Why an entry failed to decompress, see `AdatError::Decompress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecompressErrorKind {
+    InvalidData, // the compressed data is corrupt
+    OutputLimit, // it decompresses to more than the declared length
+    Truncated, // the data ends before the stream does
+    Codec // reported by a codec other than the built-in ones
+}
+
+impl AdatError {
+    /// The error without any `WithEntry` context around it.
+    pub fn without_context(&self) -> &AdatError {
+        match self {
+            AdatError::WithEntry { source, .. } => source.without_context(),
+            e => e
+        }
+    }
+
+    // the AdatError an io::Error was made from, or the io::Error back if it wraps none
+    pub(crate) fn unwrap_io(e: std::io::Error) -> std::result::Result<AdatError, std::io::Error> {
+        if !e.get_ref().is_some_and(|inner| inner.is::<AdatError>()) {
+            return Err(e);
+        }
+        match e.into_inner().map(|inner| inner.downcast::<AdatError>()) {
+            Some(Ok(inner)) => Ok(*inner),
+            _ => unreachable!("checked to wrap an AdatError above")
+        }
+    }
+
+    // unwraps what an EntryReader reported through io::Error, keeping real I/O errors as such
+    pub(crate) fn from_stream(e: std::io::Error) -> AdatError {
+        AdatError::unwrap_io(e).unwrap_or_else(AdatError::Io)
+    }
+
+    // names the entry a read or decompression error happened in
+    pub(crate) fn with_entry(self, name: &str) -> AdatError {
+        match self {
+            e @ AdatError::WithEntry { .. } => e,
+            e => AdatError::WithEntry { name: name.to_string(), source: Box::new(e) }
+        }
+    }
+}
+
+impl fmt::Display for AdatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdatError::MagicMismatch { found } => write!(f, "ADAT magic mismatch, found: {:?}", found),
+            AdatError::UnsupportedVersion(version) => write!(f, "unsupported ADAT version: {}", version),
+            AdatError::EmptyToc => write!(f, "empty toc"),
+            AdatError::TocMisaligned(toc_length) => {
+                write!(f, "toc length {} is not a multiple of the entry size {}", toc_length, crate::ADAT_ENTRY_SIZE)
+            }
+            AdatError::TocOutOfBounds { toc_offset, toc_length, file_len } => {
+                write!(f, "toc at {} with length {} exceeds the file length {}", toc_offset, toc_length, file_len)
+            }
+            AdatError::EntryOutOfBounds { name, offset, compressed_length, file_len } => {
+                write!(f, "entry {} at {} with compressed length {} exceeds the file length {}", name, offset, compressed_length, file_len)
+            }
+            AdatError::EntryOverlapsToc { name, offset, compressed_length, toc_offset, toc_length } => {
+                write!(f, "entry {} at {} with compressed length {} overlaps the toc at {} with length {}", name, offset, compressed_length, toc_offset, toc_length)
+            }
+            AdatError::EntryNotFound(name) => write!(f, "entry not found: {}", name),
+            AdatError::AmbiguousEntry { name, candidates } => {
+                write!(f, "entry name {} is ambiguous, it matches: {}", name, candidates.join(", "))
+            }
+            AdatError::IndexOutOfBounds { index, len } => write!(f, "entry index {} out of bounds, toc has {} entries", index, len),
+            AdatError::TooManyEntries { count, limit } => write!(f, "toc declares {} entries, more than the limit of {}", count, limit),
+            AdatError::NameOverLimit { name, length, limit } => {
+                write!(f, "entry name {} is {} bytes long, more than the limit of {}", name, length, limit)
+            }
+            AdatError::EntryTooLarge { name, length, limit } => {
+                write!(f, "entry {} declares {} bytes, more than the limit of {}", name, length, limit)
+            }
+            AdatError::Decompress { message, .. } => write!(f, "failed to decompress entry: {}", message),
+            AdatError::LengthMismatch { expected, got } => {
+                write!(f, "entry decompressed to {} bytes instead of its declared {}", got, expected)
+            }
+            AdatError::InvalidName(e) => write!(f, "entry name is not valid UTF-8: {}", e),
+            AdatError::InvalidText(e) => write!(f, "entry is not valid UTF-8 text: {}", e),
+            AdatError::NameTooLong(name) => write!(f, "entry name is longer than 128 bytes: {}", name),
+            AdatError::UnterminatedName(name) => write!(f, "entry name fills all 128 bytes without a null terminator: {}", name),
+            AdatError::InvalidEntryName(name) => write!(f, "invalid entry name: {:?}", name),
+            AdatError::DuplicateEntry(name) => write!(f, "duplicate entry name: {}", name),
+            AdatError::UnsafeEntryPath(name) => write!(f, "refusing to extract unsafe entry path: {}", name),
+            AdatError::SizeOverflow { what, value } => write!(f, "{} does not fit in 32 bits: {}", what, value),
+            AdatError::WithEntry { name, source } => write!(f, "failed to read entry '{}': {}", name, source),
+            AdatError::Io(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl std::error::Error for AdatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AdatError::InvalidName(e) | AdatError::InvalidText(e) => Some(e),
+            AdatError::WithEntry { source, .. } => Some(&**source),
+            AdatError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for AdatError {
+    fn from(e: std::io::Error) -> Self {
+        AdatError::Io(e)
+    }
+}
+
+// lets AdatError flow through std::io::Read implementations and io::Result based callers
+impl From<AdatError> for std::io::Error {
+    fn from(e: AdatError) -> Self {
+        // entry context is kept, with the kind of the error it wraps
+        let kind = match e.without_context() {
+            AdatError::Io(inner) => inner.kind(),
+            AdatError::EntryNotFound(_) | AdatError::IndexOutOfBounds { .. } => ErrorKind::NotFound,
+            AdatError::LengthMismatch { .. } => ErrorKind::UnexpectedEof,
+            _ => ErrorKind::InvalidData
+        };
+
+        match e {
+            AdatError::Io(e) => e,
+            e => std::io::Error::new(kind, e)
+        }
+    }
+}
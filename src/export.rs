@@ -0,0 +1,50 @@
+use std::io::{self, Seek, Read, Write};
+
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::{Result, Package};
+
+impl<T: Read + Seek> Package<'_, T> {
+    /// Writes every entry reachable by name into a deflate-compressed zip
+    /// archive, under the same `/`-separated name, in TOC order.
+    pub fn export_zip<W: Write + Seek>(&mut self, out: W) -> Result<W> {
+        let mut zip = ZipWriter::new(out);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        let mut data: Vec<u8> = Vec::new();
+
+        for position in self.toc.reachable_positions() {
+            let (pe, codec, cursor) = self.readable_entry_at(position)?;
+            pe.read_entry_into(cursor, codec, &mut data)?;
+
+            zip.start_file(pe.path.as_str(), options).map_err(io::Error::from)?;
+            zip.write_all(&data)?;
+        }
+
+        Ok(zip.finish().map_err(io::Error::from)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{package_with, Package};
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn exports_zip() {
+        let mut cursor = package_with(&[("some/path/foo.txt", b"foo"), ("bar.bin", &[1; 4096])]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let out = package.export_zip(Cursor::new(Vec::new())).unwrap();
+        let mut zip = zip::ZipArchive::new(out).unwrap();
+        assert_eq!(zip.len(), 2);
+
+        let mut text = String::new();
+        zip.by_name("some/path/foo.txt").unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "foo");
+
+        let mut data = Vec::new();
+        zip.by_name("bar.bin").unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, vec![1; 4096]);
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Seek, Read, Write, BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::{AdatError, Result, Codec, ExtractOptions, OverwritePolicy, Package, PackageEntry};
+
+/// A file `Package::plan_extraction` found would be written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedWrite {
+    pub name: String, // of the entry
+    pub path: PathBuf, // sanitized output path
+    pub size: u64, // decompressed length
+    pub overwrites: bool, // something already exists at path
+    pub collides: bool // an entry earlier in the plan is written to path too
+}
+
+/// Maps an entry name onto a path below `dest`, the way `extract_all` does.
+///
+/// Returns `None` for names that could escape `dest`: absolute paths, `..`
+/// components, drive letters and anything else with a `:`, and names with
+/// no components at all. Both `/` and `\\` count as separators.
+pub fn sanitize_entry_path(name: &str, dest: impl AsRef<Path>) -> Option<PathBuf> {
+    if name.starts_with(['/', '\\']) {
+        return None; // rooted, or a UNC path
+    }
+
+    let mut path = dest.as_ref().to_path_buf();
+    let mut has_components = false;
+
+    for component in name.split(['/', '\\']) {
+        match component {
+            "" | "." => continue, // doubled or trailing separators
+            ".." => return None,
+            c if c.contains(':') => return None, // drive letters, alternate data streams
+            c => {
+                path.push(c);
+                has_components = true;
+            }
+        }
+    }
+
+    if has_components { Some(path) } else { None }
+}
+
+// with OverwritePolicy::Error, fails on the first path that exists already or that
+// several entries go to, e.g. when flattened, before anything is written
+fn check_overwrites(targets: &[(String, PathBuf)], options: &ExtractOptions) -> Result<()> {
+    if options.overwrite != OverwritePolicy::Error {
+        return Ok(());
+    }
+
+    let mut seen: HashSet<&Path> = HashSet::with_capacity(targets.len());
+    match targets.iter().find(|(_, path)| path.exists() || !seen.insert(path)) {
+        Some((_, path)) => Err(std::io::Error::new(ErrorKind::AlreadyExists, format!("{} already exists", path.display())).into()),
+        None => Ok(())
+    }
+}
+
+// opens the file an entry is written to, or None if it was skipped as already there
+fn create_entry_file(path: &Path, options: &ExtractOptions) -> Result<Option<File>> {
+    if let Some(parent) = path.parent().filter(|_| options.create_dirs) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut open = File::options();
+    match options.overwrite {
+        OverwritePolicy::Overwrite => open.write(true).create(true).truncate(true),
+        OverwritePolicy::Skip | OverwritePolicy::Error => open.write(true).create_new(true)
+    };
+    match open.open(path) {
+        Err(e) if e.kind() == ErrorKind::AlreadyExists && options.overwrite == OverwritePolicy::Skip => Ok(None),
+        file => Ok(Some(file?))
+    }
+}
+
+// writes the entry to path, returning false if it was skipped as already there
+fn write_entry_file<T: Read + Seek>(pe: &PackageEntry, codec: &dyn Codec, cursor: &mut T, path: &Path, options: &ExtractOptions) -> Result<bool> {
+    let Some(file) = create_entry_file(path, options)? else {
+        return Ok(false);
+    };
+
+    let mut file = BufWriter::new(file);
+    pe.copy_entry_to(cursor, codec, &mut file)?;
+    file.flush()?;
+    Ok(true)
+}
+
+// like write_entry_file, for compressed data read already, decompressing it into data
+fn write_decoded_file(pe: &PackageEntry, codec: &dyn Codec, compressed_data: &[u8], data: &mut Vec<u8>, path: &Path, options: &ExtractOptions) -> Result<bool> {
+    pe.decode(codec, compressed_data, data)?;
+    let Some(mut file) = create_entry_file(path, options)? else {
+        return Ok(false);
+    };
+
+    file.write_all(data)?;
+    Ok(true)
+}
+
+impl<T: Read + Seek> Package<'_, T> {
+    /// Extracts every entry below `dest`, creating directories for the
+    /// `/`-separated entry names as needed.
+    ///
+    /// Names that `sanitize_entry_path` rejects fail the extraction before
+    /// anything is written. Returns the number of files written.
+    pub fn extract_all(&mut self, dest: impl AsRef<Path>) -> Result<usize> {
+        self.extract_all_with(dest, &ExtractOptions::default())
+    }
+
+    /// Like `extract_all`, with control over existing files, directory
+    /// creation and flattening. Returns the number of files written, which
+    /// leaves out the ones skipped.
+    ///
+    /// With `OverwritePolicy::Error`, an existing file fails the extraction
+    /// before anything is written. When flattening, entries sharing a file
+    /// name end up at the same path and the policy decides between them too.
+    pub fn extract_all_with(&mut self, dest: impl AsRef<Path>, options: &ExtractOptions) -> Result<usize> {
+        let targets = self.extraction_targets_with(dest.as_ref(), options)?;
+        check_overwrites(&targets, options)?;
+        self.extract_targets(&targets, options, &mut |_, _, _| {})
+    }
+
+    /// Like `extract_all`, calling `progress` with the entry name, the bytes
+    /// written so far and the total decompressed bytes, before and after each entry.
+    pub fn extract_all_with_progress(&mut self, dest: impl AsRef<Path>, mut progress: impl FnMut(&str, u64, u64)) -> Result<usize> {
+        let targets = self.extraction_targets(dest.as_ref())?;
+        self.extract_targets(&targets, &ExtractOptions::default(), &mut progress)
+    }
+
+    /// Like `extract_all`, but reads the entries in ascending offset order,
+    /// so the cursor mostly moves forward instead of seeking back and forth.
+    pub fn extract_all_sequential(&mut self, dest: impl AsRef<Path>) -> Result<usize> {
+        let mut targets = self.extraction_targets(dest.as_ref())?;
+        targets.sort_by_key(|(name, _)| self.toc.entry(name).map(|pe| pe.offset));
+        self.extract_targets(&targets, &ExtractOptions::default(), &mut |_, _, _| {})
+    }
+
+    /// Like `extract_all_sequential`, but overlaps reading with decompressing:
+    /// this thread reads the compressed data of up to `depth` entries ahead
+    /// while another decompresses and writes the ones already read.
+    ///
+    /// Holds at most `depth` entries' compressed data, plus the one being
+    /// decompressed, in memory at a time.
+    pub fn extract_all_prefetch(&mut self, dest: impl AsRef<Path>, depth: usize) -> Result<usize> {
+        self.extract_all_prefetch_with(dest, depth, &ExtractOptions::default())
+    }
+
+    /// Like `extract_all_prefetch`, with the options of `extract_all_with`.
+    /// Returns the number of files written, which leaves out the ones skipped.
+    pub fn extract_all_prefetch_with(&mut self, dest: impl AsRef<Path>, depth: usize, options: &ExtractOptions) -> Result<usize> {
+        let mut targets = self.extraction_targets_with(dest.as_ref(), options)?;
+        check_overwrites(&targets, options)?;
+        targets.sort_by_key(|(name, _)| self.toc.entry(name).map(|pe| pe.offset));
+
+        let toc = self.toc.clone();
+        let mut jobs: Vec<(&PackageEntry, &Path)> = Vec::with_capacity(targets.len());
+        for (name, path) in &targets {
+            jobs.push((toc.readable_entry(name, self.max_decompressed_size)?, path));
+        }
+
+        let cursor = self.cursor.get_mut();
+        let (sender, receiver) = mpsc::sync_channel::<(&PackageEntry, &Path, Vec<u8>)>(depth);
+
+        thread::scope(|scope| {
+            let writer = scope.spawn(|| -> Result<usize> {
+                let mut data: Vec<u8> = Vec::new();
+                let mut written: usize = 0;
+                for (pe, path, compressed_data) in receiver {
+                    let write = write_decoded_file(pe, &*toc.codec, &compressed_data, &mut data, path, options);
+                    if write.map_err(|e| e.with_entry(&pe.path))? {
+                        written += 1;
+                    }
+                }
+                Ok(written)
+            });
+
+            let mut read = Ok(());
+            for &(pe, path) in &jobs {
+                let compressed_data = match pe.read_entry_raw(cursor) {
+                    Ok(compressed_data) => compressed_data,
+                    Err(e) => {
+                        read = Err(e);
+                        break;
+                    }
+                };
+                // only fails once the writer gave up, which its result tells about
+                if sender.send((pe, path, compressed_data)).is_err() {
+                    break;
+                }
+            }
+            drop(sender);
+
+            let written = writer.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+            read.map(|_| written)
+        })
+    }
+
+    /// What `extract_all_with` would write, in the order it would, without
+    /// touching the disk: every entry's output path and size, and whether it
+    /// would replace an existing file or one written earlier in the same run.
+    ///
+    /// Fails like `extract_all` on names that `sanitize_entry_path` rejects.
+    pub fn plan_extraction(&self, dest: impl AsRef<Path>, options: &ExtractOptions) -> Result<Vec<PlannedWrite>> {
+        let targets = self.extraction_targets_with(dest.as_ref(), options)?;
+        let mut seen: HashSet<&Path> = HashSet::with_capacity(targets.len());
+
+        Ok(targets.iter().map(|(name, path)| PlannedWrite {
+            size: self.toc.entry(name).map_or(0, |pe| pe.length as u64),
+            overwrites: path.exists(),
+            collides: !seen.insert(path),
+            name: name.clone(),
+            path: path.clone()
+        }).collect())
+    }
+
+    // like extraction_targets, flattened if asked to
+    fn extraction_targets_with(&self, dest: &Path, options: &ExtractOptions) -> Result<Vec<(String, PathBuf)>> {
+        let mut targets = self.extraction_targets(dest)?;
+        if options.flatten {
+            for (_, path) in &mut targets {
+                // sanitized paths always end in a file name, below dest
+                let file_name = path.file_name().map(PathBuf::from).unwrap_or_default();
+                *path = dest.join(file_name);
+            }
+        }
+
+        Ok(targets)
+    }
+
+    // sanitized output path for every entry; fails before anything is written
+    fn extraction_targets(&self, dest: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let mut targets: Vec<(String, PathBuf)> = Vec::with_capacity(self.len());
+        for name in self.entry_names() {
+            let path = sanitize_entry_path(name, dest).ok_or_else(|| {
+                AdatError::UnsafeEntryPath(name.to_string())
+            })?;
+            targets.push((name.to_string(), path));
+        }
+
+        Ok(targets)
+    }
+
+    fn extract_targets(&mut self, targets: &[(String, PathBuf)], options: &ExtractOptions, progress: &mut dyn FnMut(&str, u64, u64)) -> Result<usize> {
+        let total = self.total_uncompressed_size();
+        let mut done: u64 = 0;
+        let mut written: usize = 0;
+
+        for (name, path) in targets {
+            progress(name, done, total);
+            let (pe, codec, cursor) = self.readable_entry(name)?;
+            if write_entry_file(pe, codec, cursor, path, options)? {
+                written += 1;
+            }
+            done += pe.length as u64;
+            progress(name, done, total);
+        }
+
+        Ok(written)
+    }
+
+    /// Like `extract_all`, but decompresses entries on the rayon thread pool.
+    ///
+    /// `archive_path` has to point at the file this package was mounted
+    /// from: every worker opens its own handle to it, so the mounted cursor
+    /// is not used at all and `&self` is enough.
+    #[cfg(feature = "rayon")]
+    pub fn extract_all_parallel(&self, dest: impl AsRef<Path>, archive_path: impl AsRef<Path>) -> Result<usize> {
+        use rayon::prelude::*;
+
+        let archive_path = archive_path.as_ref();
+        let codec = &*self.codec;
+        let targets = self.extraction_targets(dest.as_ref())?;
+
+        let mut jobs: Vec<(&PackageEntry, &Path)> = Vec::with_capacity(targets.len());
+        for (name, path) in &targets {
+            jobs.push((self.toc.readable_entry(name, self.max_decompressed_size)?, path));
+        }
+
+        jobs.par_iter().try_for_each_init(|| File::open(archive_path), |file, (pe, path)| {
+            let file = file.as_mut().map_err(|e| std::io::Error::new(e.kind(), e.to_string()))?;
+            write_entry_file(pe, codec, file, path, &ExtractOptions::default()).map(|_| ())
+        })?;
+
+        Ok(targets.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PackageWriter;
+    use std::io::Cursor;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("adat-format-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn extracts_everything() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("some/path/foo.txt", b"foo").unwrap();
+        writer.add_entry("some/./bar.txt", b"bar").unwrap();
+        writer.add_entry("baz.txt", b"baz").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let dest = test_dir("extract");
+        assert_eq!(package.extract_all(&dest).unwrap(), 3);
+
+        assert_eq!(fs::read(dest.join("some/path/foo.txt")).unwrap(), b"foo");
+        assert_eq!(fs::read(dest.join("some/bar.txt")).unwrap(), b"bar");
+        assert_eq!(fs::read(dest.join("baz.txt")).unwrap(), b"baz");
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn extracts_with_options() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a/one.txt", b"one").unwrap();
+        writer.add_entry("b/c/two.txt", b"two").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let dest = test_dir("extract-options");
+        let flat = ExtractOptions { flatten: true, ..ExtractOptions::default() };
+        assert_eq!(package.extract_all_with(&dest, &flat).unwrap(), 2);
+        assert_eq!(fs::read(dest.join("one.txt")).unwrap(), b"one");
+        assert_eq!(fs::read(dest.join("two.txt")).unwrap(), b"two");
+        assert!(!dest.join("a").exists());
+
+        fs::write(dest.join("one.txt"), b"kept").unwrap();
+        let skip = ExtractOptions { overwrite: OverwritePolicy::Skip, ..flat };
+        assert_eq!(package.extract_all_with(&dest, &skip).unwrap(), 0);
+        assert_eq!(fs::read(dest.join("one.txt")).unwrap(), b"kept");
+
+        let error = ExtractOptions { overwrite: OverwritePolicy::Error, ..ExtractOptions::default() };
+        assert_eq!(package.extract_all_with(&dest, &error).unwrap(), 2);
+        fs::remove_file(dest.join("b/c/two.txt")).unwrap();
+        assert!(matches!(package.extract_all_with(&dest, &error), Err(AdatError::Io(e)) if e.kind() == ErrorKind::AlreadyExists));
+        assert!(!dest.join("b/c/two.txt").exists()); // nothing written
+
+        assert_eq!(package.extract_all_with(&dest, &flat).unwrap(), 2);
+        assert_eq!(fs::read(dest.join("one.txt")).unwrap(), b"one");
+
+        let no_dirs = ExtractOptions { create_dirs: false, ..ExtractOptions::default() };
+        assert!(matches!(package.extract_all_with(dest.join("missing"), &no_dirs), Err(AdatError::Io(e)) if e.kind() == ErrorKind::NotFound));
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn plans_extraction() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a/same.txt", b"first").unwrap();
+        writer.add_entry("b/same.txt", b"second!").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let dest = test_dir("plan");
+        let mut plan = package.plan_extraction(&dest, &ExtractOptions::default()).unwrap();
+        plan.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(plan[0], PlannedWrite {
+            name: "a/same.txt".to_string(),
+            path: dest.join("a").join("same.txt"),
+            size: 5,
+            overwrites: false,
+            collides: false
+        });
+        assert_eq!(plan[1].size, 7);
+        assert!(!plan[1].collides);
+        assert!(!dest.exists());
+
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("same.txt"), b"there").unwrap();
+        let flat = package.plan_extraction(&dest, &ExtractOptions { flatten: true, ..ExtractOptions::default() }).unwrap();
+        assert!(flat.iter().all(|planned| planned.overwrites && planned.path == dest.join("same.txt")));
+        assert_eq!(flat.iter().filter(|planned| planned.collides).count(), 1);
+        assert!(flat[1].collides);
+
+        fs::remove_file(dest.join("same.txt")).unwrap();
+        let error = ExtractOptions { flatten: true, overwrite: OverwritePolicy::Error, ..ExtractOptions::default() };
+        assert!(matches!(package.extract_all_with(&dest, &error), Err(AdatError::Io(e)) if e.kind() == ErrorKind::AlreadyExists));
+        assert!(!dest.join("same.txt").exists());
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn extracts_with_prefetch() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        for idx in 0..30 {
+            writer.add_entry(&format!("dir{}/file{}.bin", idx % 3, idx), &vec![idx as u8; 500 * idx]).unwrap();
+        }
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        for depth in [0, 1, 8] {
+            let dest = test_dir(&format!("prefetch-{}", depth));
+            assert_eq!(package.extract_all_prefetch(&dest, depth).unwrap(), 30);
+            for idx in 0..30 {
+                let path = dest.join(format!("dir{}/file{}.bin", idx % 3, idx));
+                assert_eq!(fs::read(path).unwrap(), vec![idx as u8; 500 * idx]);
+            }
+            fs::remove_dir_all(&dest).unwrap();
+        }
+
+        // existing files are left alone, and a failed write names its entry
+        let dest = test_dir("prefetch-options");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("file1.bin"), b"kept").unwrap();
+        let skip_flat = ExtractOptions { overwrite: OverwritePolicy::Skip, flatten: true, ..ExtractOptions::default() };
+        assert_eq!(package.extract_all_prefetch_with(&dest, 4, &skip_flat).unwrap(), 29);
+        assert_eq!(fs::read(dest.join("file1.bin")).unwrap(), b"kept");
+        assert_eq!(fs::read(dest.join("file29.bin")).unwrap(), vec![29; 500 * 29]);
+        fs::remove_dir_all(&dest).unwrap();
+
+        let no_dirs = ExtractOptions { create_dirs: false, ..ExtractOptions::default() };
+        let error = package.extract_all_prefetch_with(&dest, 4, &no_dirs).unwrap_err();
+        assert!(matches!(error, AdatError::WithEntry { ref name, .. } if package.contains(name)), "{:?}", error);
+
+        let error = ExtractOptions { overwrite: OverwritePolicy::Error, flatten: true, ..ExtractOptions::default() };
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("file7.bin"), b"there").unwrap();
+        assert!(matches!(package.extract_all_prefetch_with(&dest, 4, &error), Err(AdatError::Io(e)) if e.kind() == ErrorKind::AlreadyExists));
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 1);
+        fs::remove_dir_all(&dest).unwrap();
+
+        package.set_max_decompressed_size(1000);
+        let dest = test_dir("prefetch-limited");
+        assert!(matches!(package.extract_all_prefetch(&dest, 4), Err(AdatError::EntryTooLarge { .. })));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn extracts_in_offset_order() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        for idx in 0..20 {
+            writer.add_entry(&format!("dir{}/file{}.txt", idx % 3, idx), format!("content {}", idx).as_bytes()).unwrap();
+        }
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let dest = test_dir("sequential");
+        assert_eq!(package.extract_all_sequential(&dest).unwrap(), 20);
+        for idx in 0..20 {
+            let path = dest.join(format!("dir{}/file{}.txt", idx % 3, idx));
+            assert_eq!(fs::read_to_string(path).unwrap(), format!("content {}", idx));
+        }
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn reports_progress() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a.bin", &[1; 300]).unwrap();
+        writer.add_entry("b.bin", &[2; 700]).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let dest = test_dir("progress");
+        let mut calls: Vec<(String, u64, u64)> = Vec::new();
+        package.extract_all_with_progress(&dest, |name, done, total| calls.push((name.to_string(), done, total))).unwrap();
+
+        assert_eq!(calls.len(), 4);
+        assert_eq!(calls[0].1, 0);
+        assert_eq!(calls[3].1, 1000);
+        assert!(calls.iter().all(|(_, done, total)| *total == 1000 && done <= total));
+        assert!(calls.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(calls[0].0, calls[1].0);
+        assert_eq!(calls[1].1, package.entry_info(&calls[1].0).unwrap().length as u64);
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn extracts_in_parallel() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        for idx in 0..50 {
+            writer.add_entry(&format!("dir{}/file{}.bin", idx % 4, idx), &vec![idx as u8; 1000 * idx]).unwrap();
+        }
+        let archive = writer.finalize().unwrap().into_inner();
+
+        let dest = test_dir("parallel");
+        fs::create_dir_all(&dest).unwrap();
+        let archive_path = dest.join("archive.dat");
+        fs::write(&archive_path, archive).unwrap();
+
+        let mut file = File::open(&archive_path).unwrap();
+        let package = Package::mount_from_cursor(&mut file).unwrap();
+        assert_eq!(package.extract_all_parallel(dest.join("out"), &archive_path).unwrap(), 50);
+        for idx in 0..50 {
+            let path = dest.join(format!("out/dir{}/file{}.bin", idx % 4, idx));
+            assert_eq!(fs::read(path).unwrap(), vec![idx as u8; 1000 * idx]);
+        }
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn refuses_path_traversal() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("fine.txt", b"fine").unwrap();
+        writer.add_entry("some/../../etc/passwd", b"evil").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let dest = test_dir("traversal");
+        assert!(matches!(package.extract_all(&dest), Err(AdatError::UnsafeEntryPath(_))));
+        assert!(!dest.exists());
+
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("/rooted/bar.txt", b"bar").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert!(matches!(package.extract_all(&dest), Err(AdatError::UnsafeEntryPath(_))));
+    }
+
+    #[test]
+    fn sanitizes_entry_paths() {
+        let dest = Path::new("out");
+
+        assert_eq!(sanitize_entry_path("some/path/foo.txt", dest), Some(dest.join("some").join("path").join("foo.txt")));
+        assert_eq!(sanitize_entry_path("dir\\file.txt", dest), Some(dest.join("dir").join("file.txt")));
+        assert_eq!(sanitize_entry_path("a//./b/", dest), Some(dest.join("a").join("b")));
+
+        assert_eq!(sanitize_entry_path("../escape.txt", dest), None);
+        assert_eq!(sanitize_entry_path("a/../../escape.txt", dest), None);
+        assert_eq!(sanitize_entry_path("..\\escape.txt", dest), None);
+        assert_eq!(sanitize_entry_path("/abs/path", dest), None);
+        assert_eq!(sanitize_entry_path("\\\\server\\share", dest), None);
+        assert_eq!(sanitize_entry_path("C:\\Windows\\win.ini", dest), None);
+        assert_eq!(sanitize_entry_path("C:relative", dest), None);
+        assert_eq!(sanitize_entry_path("", dest), None);
+        assert_eq!(sanitize_entry_path("./", dest), None);
+
+        // any kind of path will do
+        assert_eq!(sanitize_entry_path("a", "out"), Some(dest.join("a")));
+        assert_eq!(sanitize_entry_path("a", String::from("out")), Some(dest.join("a")));
+        assert_eq!(sanitize_entry_path("a", PathBuf::from("out")), Some(dest.join("a")));
+    }
+}
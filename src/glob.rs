@@ -0,0 +1,59 @@
+// Minimal glob matching over `/`-separated entry names.
+//
+// `?` matches one character and `*` any run of characters, neither crossing
+// a `/`; `**` matches across directories, and `**/` may also match nothing.
+
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        if let Some(after) = rest.strip_prefix('/') {
+            if glob_match(after, name) {
+                return true;
+            }
+        }
+        return name.char_indices().map(|(i, _)| i).chain(Some(name.len())).any(|i| {
+            glob_match(rest, &name[i..])
+        });
+    }
+
+    if let Some(rest) = pattern.strip_prefix('*') {
+        for (i, c) in name.char_indices() {
+            if glob_match(rest, &name[i..]) {
+                return true;
+            }
+            if c == '/' {
+                return false;
+            }
+        }
+        return glob_match(rest, "");
+    }
+
+    let mut pattern_chars = pattern.chars();
+    let mut name_chars = name.chars();
+    match (pattern_chars.next(), name_chars.next()) {
+        (None, None) => true,
+        (Some('?'), Some(c)) if c != '/' => glob_match(pattern_chars.as_str(), name_chars.as_str()),
+        (Some(p), Some(c)) if p == c => glob_match(pattern_chars.as_str(), name_chars.as_str()),
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("some/path/*.txt", "some/path/foo.txt"));
+        assert!(!glob_match("some/path/*.txt", "some/path/deeper/foo.txt"));
+        assert!(!glob_match("some/path/*.txt", "some/path/foo.bin"));
+        assert!(glob_match("some/**/*.txt", "some/path/deeper/foo.txt"));
+        assert!(glob_match("some/**/*.txt", "some/foo.txt"));
+        assert!(glob_match("**", "a/b/c"));
+        assert!(glob_match("?.txt", "ä.txt"));
+        assert!(!glob_match("?.txt", "ab.txt"));
+        assert!(!glob_match("?", "/"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+        assert!(glob_match("*", ""));
+    }
+}
@@ -0,0 +1,80 @@
+use std::io::{Seek, Read};
+
+use crate::{Result, EntryInfo, Package};
+use crate::text_from_bytes;
+
+/// An entry of a mounted archive, found by `Package::entry` and read on demand.
+///
+/// Holds on to the package mutably, since reading needs its cursor; drop the
+/// handle to use the package again.
+#[derive(Debug)]
+pub struct EntryHandle<'p, 'b, T: Read + Seek> {
+    package: &'p mut Package<'b, T>,
+    position: usize // in the TOC, of the entry the name resolved to
+}
+
+impl<T: Read + Seek> EntryHandle<'_, '_, T> {
+    /// Name of the entry, as indexed.
+    pub fn name(&self) -> &str {
+        &self.package.toc.entries[self.position].path
+    }
+
+    pub fn info(&self) -> EntryInfo {
+        self.package.toc.entries[self.position].info()
+    }
+
+    /// Decompressed length, as declared in the TOC.
+    pub fn len(&self) -> u64 {
+        self.package.toc.entries[self.position].length as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn read(&mut self) -> Result<Vec<u8>> {
+        self.package.read_entry_by_index(self.position)
+    }
+
+    pub fn read_text(&mut self) -> Result<String> {
+        self.read().and_then(text_from_bytes)
+    }
+}
+
+impl<'b, T: Read + Seek> Package<'b, T> {
+    /// Short for `read_entry`.
+    pub fn get(&mut self, entry_path: &str) -> Result<Vec<u8>> {
+        self.read_entry(entry_path)
+    }
+
+    /// A handle to the entry a name resolves to, or `None` if there is none.
+    pub fn entry(&mut self, entry_path: &str) -> Option<EntryHandle<'_, 'b, T>> {
+        let position = self.toc.position(entry_path).ok()?;
+        Some(EntryHandle { package: self, position })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AdatError, Package, PackageWriter};
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_through_handles() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a.txt", b"handle").unwrap();
+        writer.add_entry("b.bin", &[7; 5000]).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert_eq!(package.get("a.txt").unwrap(), b"handle");
+        assert!(matches!(package.get("c"), Err(AdatError::EntryNotFound(_))));
+        assert!(package.entry("c").is_none());
+
+        let mut handle = package.entry("b.bin").unwrap();
+        assert_eq!((handle.name(), handle.len()), ("b.bin", 5000));
+        assert!(!handle.is_empty() && handle.info().compressed_length < 5000);
+        assert_eq!(handle.read().unwrap(), vec![7; 5000]);
+        assert_eq!(package.entry("a.txt").unwrap().read_text().unwrap(), "handle");
+    }
+}
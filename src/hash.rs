@@ -0,0 +1,57 @@
+use std::io::{self, Seek, Read, Write};
+
+use crc32fast::Hasher as Crc32;
+use sha2::{Digest, Sha256};
+
+use crate::{Result, Package};
+
+// hands everything written to it to a hasher's update
+struct HashWriter<F: FnMut(&[u8])>(F);
+
+impl<F: FnMut(&[u8])> Write for HashWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.0)(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: Read + Seek> Package<'_, T> {
+    /// SHA-256 digest of the decompressed contents of an entry, computed
+    /// while streaming it.
+    pub fn entry_sha256(&mut self, entry_path: &str) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        self.read_entry_to_writer(entry_path, &mut HashWriter(|buf: &[u8]| hasher.update(buf)))?;
+        Ok(hasher.finalize().into())
+    }
+
+    /// CRC-32 (IEEE) checksum of the decompressed contents of an entry,
+    /// computed while streaming it.
+    pub fn entry_crc32(&mut self, entry_path: &str) -> Result<u32> {
+        let mut hasher = Crc32::new();
+        self.read_entry_to_writer(entry_path, &mut HashWriter(|buf: &[u8]| hasher.update(buf)))?;
+        Ok(hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{package_with, AdatError, Package};
+
+    #[test]
+    fn hashes_entries() {
+        let mut cursor = package_with(&[("abc.txt", b"abc"), ("big.bin", &[0x61; 100_000])]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let digest = package.entry_sha256("abc.txt").unwrap();
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(package.entry_crc32("abc.txt").unwrap(), 0x352441c2);
+
+        assert_eq!(package.entry_crc32("big.bin").unwrap(), crc32fast::hash(&[0x61; 100_000]));
+        assert!(matches!(package.entry_sha256("missing"), Err(AdatError::EntryNotFound(_))));
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::{hash_map, HashMap};
+use std::slice;
+
+use crate::PackageEntry;
+
+// maps every name to the position in entries of the last entry carrying it
+#[derive(Debug)]
+pub(crate) enum NameIndex {
+    Hashed(HashMap<String, usize>),
+    Sorted(Vec<usize>) // positions, ordered by the name of the entry at each; names are not copied
+}
+
+impl NameIndex {
+    pub(crate) fn get(&self, entries: &[PackageEntry], name: &str) -> Option<usize> {
+        match self {
+            NameIndex::Hashed(index) => index.get(name).copied(),
+            NameIndex::Sorted(positions) => {
+                let found = positions.binary_search_by(|&position| entries[position].path.as_str().cmp(name));
+                found.ok().map(|i| positions[i])
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            NameIndex::Hashed(index) => index.len(),
+            NameIndex::Sorted(positions) => positions.len()
+        }
+    }
+
+    // names with their positions; in arbitrary order when hashed, by name when sorted
+    pub(crate) fn iter<'a>(&'a self, entries: &'a [PackageEntry]) -> Iter<'a> {
+        match self {
+            NameIndex::Hashed(index) => Iter::Hashed(index.iter()),
+            NameIndex::Sorted(positions) => Iter::Sorted(positions.iter(), entries)
+        }
+    }
+
+    // positions of all indexed entries, in the same order as iter
+    pub(crate) fn positions<'a>(&'a self, entries: &'a [PackageEntry]) -> impl Iterator<Item = usize> + 'a {
+        self.iter(entries).map(|(_, position)| position)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Iter<'a> {
+    Hashed(hash_map::Iter<'a, String, usize>),
+    Sorted(slice::Iter<'a, usize>, &'a [PackageEntry])
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, usize);
+
+    fn next(&mut self) -> Option<(&'a str, usize)> {
+        match self {
+            Iter::Hashed(inner) => inner.next().map(|(name, &position)| (name.as_str(), position)),
+            Iter::Sorted(inner, entries) => inner.next().map(|&position| (entries[position].path.as_str(), position))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Iter::Hashed(inner) => inner.size_hint(),
+            Iter::Sorted(inner, _) => inner.size_hint()
+        }
+    }
+}
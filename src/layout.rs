@@ -0,0 +1,92 @@
+use std::ops::Range;
+
+use crate::{Toc, ADAT_HEADER_SIZE};
+
+/// Where the bytes of an archive go, see `Toc::layout_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutReport {
+    pub file_len: u64,
+    pub data_bytes: u64, // covered by the data of at least one TOC entry, shared blobs counted once
+    pub gap_bytes: u64, // covered by neither the header, entry data nor the TOC
+    pub gaps: Vec<Range<u64>> // in offset order, adjacent gaps merged
+}
+
+impl Toc {
+    /// Accounts for every byte of the archive: the header, entry data, the
+    /// TOC, and the gaps between them, such as the blobs of replaced entries
+    /// and old TOCs left behind by `PackageWriter::append`, or trailing bytes.
+    ///
+    /// Shadowed duplicates still count as data. The gap bytes are what a
+    /// repack with `PackageEditor` would save at least.
+    pub fn layout_report(&self) -> LayoutReport {
+        let mut data: Vec<Range<u64>> = self.entries.iter().filter(|pe| pe.compressed_length > 0).map(|pe| {
+            pe.offset as u64..pe.offset as u64 + pe.compressed_length as u64
+        }).collect();
+        data.sort_unstable_by_key(|range| range.start);
+
+        let mut data_bytes = 0;
+        let mut data_end = 0;
+        for range in &data {
+            data_bytes += range.end.saturating_sub(range.start.max(data_end));
+            data_end = data_end.max(range.end);
+        }
+
+        let toc_offset = self.header.toc_offset as u64;
+        let mut used = data;
+        used.push(0..ADAT_HEADER_SIZE as u64);
+        used.push(toc_offset..toc_offset + self.header.toc_length as u64);
+        used.sort_unstable_by_key(|range| range.start);
+
+        let mut gaps: Vec<Range<u64>> = Vec::new();
+        let mut end = 0;
+        for range in &used {
+            if range.start > end {
+                gaps.push(end..range.start);
+            }
+            end = end.max(range.end);
+        }
+        if self.file_len > end {
+            gaps.push(end..self.file_len);
+        }
+
+        LayoutReport {
+            file_len: self.file_len,
+            data_bytes,
+            gap_bytes: gaps.iter().map(|gap| gap.end - gap.start).sum(),
+            gaps
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{package_with, Package, PackageWriter};
+
+    #[test]
+    fn reports_gaps() {
+        let mut cursor = package_with(&[("a.txt", b"kept"), ("replaced.bin", &[1; 5000])]);
+
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let report = package.layout_report();
+        assert!(report.gaps.is_empty());
+        assert_eq!(report.data_bytes, package.total_compressed_size());
+        let (replaced_at, original_len) = (package.entry_info("replaced.bin").unwrap().offset as u64, report.file_len);
+        drop(package);
+
+        // the replaced blob and the old TOC become one gap, trailing bytes another
+        let mut writer = PackageWriter::append(cursor).unwrap();
+        writer.replace_existing(true);
+        writer.add_entry("replaced.bin", &[2; 5000]).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        cursor.get_mut().extend_from_slice(&[0; 7]);
+
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let report = package.layout_report();
+        let file_len = report.file_len;
+        assert_eq!(report.gaps, vec![replaced_at..original_len, file_len - 7..file_len]);
+        assert_eq!(report.gap_bytes, original_len - replaced_at + 7);
+        assert_eq!(report.data_bytes, package.total_compressed_size());
+        assert_eq!(16 + report.data_bytes + package.toc_length() as u64 + report.gap_bytes, file_len);
+    }
+}
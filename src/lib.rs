@@ -1,17 +1,119 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::{Seek, Read, SeekFrom};
-use std::io::{Error, ErrorKind};
+use std::io::{Seek, Read, Write, SeekFrom, Take};
+use std::ops::Deref;
+use std::sync::Arc;
 
-use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+use cache::EntryCache;
+use codec::decode_entry;
+use stream::STREAM_BUFFER_SIZE;
+
+#[macro_use]
+mod trace;
+
+#[cfg(any(unix, windows))]
+mod accessor;
+#[cfg(feature = "tokio")]
+mod async_package;
+mod builder;
+mod cache;
+mod codec;
+mod diff;
+mod editor;
+mod error;
+#[cfg(feature = "zip")]
+mod export;
+mod extract;
+mod glob;
+mod handle;
+#[cfg(feature = "hashing")]
+mod hash;
+mod index;
+mod layout;
+mod options;
+mod owned;
+mod shared;
+mod slice;
+mod stream;
+mod stream_writer;
+mod summary;
+mod toc;
+mod tree;
+#[cfg(feature = "vfs")]
+mod virtual_fs;
+mod writer;
+
+#[cfg(any(unix, windows))]
+pub use accessor::EntryAccessor;
+#[cfg(feature = "tokio")]
+pub use async_package::AsyncPackage;
+pub use builder::PackageBuilder;
+pub use codec::{Codec, ZlibCodec};
+#[cfg(feature = "zstd")]
+pub use codec::ZstdCodec;
+pub use diff::PackageDiff;
+pub use editor::PackageEditor;
+pub use error::{AdatError, DecompressErrorKind, Result};
+pub use extract::{sanitize_entry_path, PlannedWrite};
+pub use handle::EntryHandle;
+pub use layout::LayoutReport;
+pub use options::{ExtractOptions, FormatSpec, MountLimits, MountOptions, OverwritePolicy};
+pub use owned::{OwnedPackage, ReadSeek};
+pub use shared::{SharedPackage, SharedReader};
+pub use slice::SlicePackage;
+#[cfg(feature = "mmap")]
+pub use slice::MmapPackage;
+pub use stream::{EntryReader, SeekableEntryReader};
+pub use stream_writer::PackageStreamWriter;
+pub use summary::Summary;
+pub use toc::{SizeKind, Toc};
+pub use tree::{DirEntry, TreeNode};
+pub use writer::{build_archive, PackageWriter};
+#[cfg(test)]
+pub(crate) use writer::package_with;
 
 const ADAT_MAGIC: [u8; 4] = [ 65, 68, 65, 84 ]; // ADAT
+const ADAT_VERSION: u32 = 9;
+const ADAT_HEADER_SIZE: u32 = 4 + 4 + 4 + 4; // raw sizeof PackageHeader
 const ADAT_ENTRY_SIZE: u32 = 128 + 4 + 4 + 4 + 4; // raw sizeof PackageEntry
 
+/// The four bytes every ADAT archive starts with.
+pub const MAGIC: [u8; 4] = ADAT_MAGIC;
+/// Size in bytes of a single TOC entry.
+pub const ENTRY_SIZE: u32 = ADAT_ENTRY_SIZE;
+
+/// Whether `bytes` start with the ADAT magic; a cheap check for file type
+/// detection that says nothing about the rest of the archive.
+pub fn is_adat(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+// whether a buffer of size bytes can be allocated at all on this platform
+fn fits_in_memory(size: u64) -> bool {
+    usize::try_from(size).is_ok_and(|size| size <= isize::MAX as usize)
+}
+
+/// A mounted archive reading entries from a cursor it borrows.
+///
+/// Dereferences to the archive's `Toc`, which holds all the metadata.
+///
+/// It is `Send` when `T` is, and `Sync` when `T` is. Reads take `&mut self`,
+/// so sharing one between threads only gives access to the metadata; see
+/// `reader_handle` for concurrent reads.
 #[derive(Debug)]
 pub struct Package<'b, T: Read + Seek> {
-    cursor: &'b mut T,
-    header: PackageHeader,
-    entries: HashMap<String, PackageEntry>
+    cursor: PackageCursor<'b, T>,
+    toc: Arc<Toc>, // shared with the handles from reader_handle
+    max_decompressed_size: Option<usize>,
+    cache: EntryCache,
+    scratch: Vec<u8> // compressed data of the last entry read, kept for its allocation
+}
+
+// the reader a package reads from: borrowed from the caller or owned by an OwnedPackage
+#[derive(Debug)]
+enum PackageCursor<'b, T> {
+    Borrowed(&'b mut T),
+    Owned(T)
 }
 
 #[derive(Debug)]
@@ -24,13 +126,79 @@ struct PackageHeader {
 
 #[derive(Debug)]
 struct PackageEntry {
+    path: String, // decoded name, what the entry is looked up by
     name: [u8; 128], // file name
     offset: u32, // offset in DAT for the file
     length: usize, // decompressed length
     compressed_length: usize, // length in the DAT file
-    u0: u32 // ??
+    u0: u32 // unknown; seems to correlate with the name, maybe a hash of it
+}
+
+/// Metadata of a single entry, as stored in the TOC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryInfo {
+    pub name: String, // lossily decoded if the stored name is not UTF-8
+    pub name_bytes: Vec<u8>, // exact stored name, up to the first null byte or all 128 bytes without one
+    pub offset: u32, // offset of the compressed data in the archive
+    pub length: u32, // decompressed length
+    pub compressed_length: u32, // length in the archive
+    pub u0: u32 // unknown, passed through as is
+}
+
+impl EntryInfo {
+    /// Whether the data is stored uncompressed, told by a compressed length
+    /// equal to the decompressed one. Reads copy such entries as they are.
+    pub fn is_stored(&self) -> bool {
+        self.compressed_length == self.length
+    }
+}
+
+/// Header fields of an archive, see `Package::probe_header`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderInfo {
+    pub magic: [u8; 4],
+    pub version: u32,
+    pub toc_offset: u32,
+    pub toc_length: u32
+}
+
+/// A TOC entry that `Package::mount_lenient` left out, and why.
+#[derive(Debug)]
+pub struct RejectedEntry {
+    pub position: usize, // in the TOC as stored
+    pub info: EntryInfo,
+    pub error: AdatError
+}
+
+/// Listing of a whole archive, see `Toc::manifest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Manifest {
+    pub version: u32,
+    pub entries: Vec<EntryInfo> // in TOC order, duplicates included
 }
 
+/// Iterator over the entry names of a package, see `Toc::entry_names`.
+#[derive(Debug, Clone)]
+pub struct EntryNames<'a> {
+    inner: index::Iter<'a>
+}
+
+impl<'a> Iterator for EntryNames<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.inner.next().map(|(name, _)| name)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for EntryNames<'_> {}
+
 // helper function for conversions
 fn u32le_from_slice(acc: &[u8]) -> u32 {
     let mut bu4: [u8; 4] = [0; 4];
@@ -38,41 +206,186 @@ fn u32le_from_slice(acc: &[u8]) -> u32 {
     u32::from_le_bytes(bu4)
 }
 
+fn text_from_bytes(data: Vec<u8>) -> Result<String> {
+    String::from_utf8(data).map_err(|e| {
+        AdatError::InvalidText(e.utf8_error())
+    })
+}
+
+fn text_from_bytes_lossy(data: Vec<u8>) -> String {
+    String::from_utf8(data).unwrap_or_else(|e| {
+        String::from_utf8_lossy(e.as_bytes()).into_owned()
+    })
+}
+
+impl<T> PackageCursor<'_, T> {
+    #[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+    fn get_ref(&self) -> &T {
+        match self {
+            PackageCursor::Borrowed(cursor) => cursor,
+            PackageCursor::Owned(cursor) => cursor
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        match self {
+            PackageCursor::Borrowed(cursor) => cursor,
+            PackageCursor::Owned(cursor) => cursor
+        }
+    }
+}
+
 impl PackageEntry {
-    pub fn get_name(&self) -> Result<&str, std::str::Utf8Error> { 
-        core::str::from_utf8(&self.name).map(|s| {
-            s.trim_end_matches(char::from(0))
-        })
+    // the name ends at the first null byte, as a C string would; without one it fills the field
+    pub fn name_bytes(&self) -> &[u8] {
+        let end = self.name.iter().position(|&b| b == 0).unwrap_or(self.name.len());
+        &self.name[..end]
     }
 
-    pub fn read_entry<T: Read + Seek>(&self, cursor: &mut T) -> std::io::Result<Vec<u8>> {
-        let mut compressed_data: Vec<u8> = vec![0; self.compressed_length];
+    // whether the name field holds a null byte, see Toc::unterminated_names
+    fn is_name_terminated(&self) -> bool {
+        self.name.contains(&0)
+    }
 
-        cursor.seek(SeekFrom::Start(self.offset as u64))?;
-        cursor.read_exact(&mut compressed_data)?;
+    pub fn get_name(&self) -> std::result::Result<&str, std::str::Utf8Error> {
+        core::str::from_utf8(self.name_bytes())
+    }
 
-        decompress_to_vec_zlib_with_limit(&compressed_data, self.length).map_err(|e| {
-            Error::new(ErrorKind::Other, e.to_string())
-        })
+    // the name to index the entry by; from_utf8_lossy borrows when the name is valid UTF-8
+    fn display_name(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.name_bytes())
+    }
+
+    fn info(&self) -> EntryInfo {
+        EntryInfo {
+            name: self.path.clone(),
+            name_bytes: self.name_bytes().to_vec(),
+            offset: self.offset,
+            length: self.length as u32,
+            compressed_length: self.compressed_length as u32,
+            u0: self.u0
+        }
+    }
+
+    // written without compression, see decode_entry
+    fn is_stored(&self) -> bool {
+        self.compressed_length == self.length
+    }
+
+    // whether the compressed data shares any byte with the range of length bytes at offset
+    fn overlaps(&self, offset: u32, length: u32) -> bool {
+        let (start, end) = (self.offset as u64, self.offset as u64 + self.compressed_length as u64);
+        start < offset as u64 + length as u64 && (offset as u64) < end
+    }
+
+    // fails if the declared decompressed length is over limit
+    fn check_length(&self, limit: Option<usize>) -> Result<()> {
+        match limit {
+            Some(limit) if self.length > limit => Err(AdatError::EntryTooLarge {
+                name: self.path.clone(),
+                length: self.length as u64,
+                limit: limit as u64
+            }),
+            _ => Ok(())
+        }
+    }
+
+    pub fn read_entry<T: Read + Seek>(&self, cursor: &mut T, codec: &dyn Codec) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::new();
+        self.read_entry_into(cursor, codec, &mut data)?;
+        Ok(data)
+    }
+
+    pub fn read_entry_into<T: Read + Seek>(&self, cursor: &mut T, codec: &dyn Codec, out: &mut Vec<u8>) -> Result<usize> {
+        self.read_entry_with(cursor, codec, &mut Vec::new(), out)
+    }
+
+    // like read_entry_into, reading the compressed data into scratch
+    fn read_entry_with<T: Read + Seek>(&self, cursor: &mut T, codec: &dyn Codec, scratch: &mut Vec<u8>, out: &mut Vec<u8>) -> Result<usize> {
+        trace_span!("read_entry", entry = %self.path);
+        self.read_entry_raw_into(cursor, scratch)?;
+        self.decode(codec, scratch, out)
+    }
+
+    // decompresses compressed_data read for this entry
+    fn decode(&self, codec: &dyn Codec, compressed_data: &[u8], out: &mut Vec<u8>) -> Result<usize> {
+        let result = decode_entry(codec, compressed_data, self.length, out);
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(length) => tracing::trace!(entry = %self.path, length, stored = self.is_stored(), "decompressed"),
+            Err(e) => tracing::debug!(entry = %self.path, error = %e, "decompression failed")
+        }
+
+        result.map_err(|e| e.with_entry(&self.path))
+    }
+
+    pub fn read_entry_raw<T: Read + Seek>(&self, cursor: &mut T) -> Result<Vec<u8>> {
+        let mut compressed_data: Vec<u8> = Vec::new();
+        self.read_entry_raw_into(cursor, &mut compressed_data)?;
+        Ok(compressed_data)
+    }
+
+    // replaces the contents of buffer with the compressed data, reusing its allocation
+    fn read_entry_raw_into<T: Read + Seek>(&self, cursor: &mut T, buffer: &mut Vec<u8>) -> Result<()> {
+        trace_trace!(entry = %self.path, offset = self.offset, compressed_length = self.compressed_length, "reading entry data");
+        buffer.clear();
+        buffer.resize(self.compressed_length, 0);
+
+        cursor.seek(SeekFrom::Start(self.offset as u64))
+            .and_then(|_| cursor.read_exact(buffer))
+            .map_err(|e| AdatError::from(e).with_entry(&self.path))
+    }
+
+    // streams the decompressed data into out, returning the number of bytes written
+    fn copy_entry_to<T: Read + Seek, W: Write + ?Sized>(&self, cursor: &mut T, codec: &dyn Codec, out: &mut W) -> Result<u64> {
+        let mut reader = self.open_entry(cursor, codec)?;
+        std::io::copy(&mut reader, out).map_err(|e| AdatError::from_stream(e).with_entry(&self.path))
+    }
+
+    pub fn open_entry<'a, T: Read + Seek>(&self, cursor: &'a mut T, codec: &dyn Codec) -> Result<EntryReader<'a, T>> {
+        self.open_entry_with(cursor, codec, STREAM_BUFFER_SIZE)
+    }
+
+    /// Like `open_entry`, but the reader can also seek, see `SeekableEntryReader`.
+    pub fn open_entry_seekable<'a, T: Read + Seek>(&self, cursor: &'a mut T, codec: &dyn Codec) -> Result<SeekableEntryReader<'a, T>> {
+        let reader = self.open_entry(cursor, codec)?;
+        Ok(SeekableEntryReader::new(reader, self.offset as u64, self.compressed_length as u64))
+    }
+
+    // streams deflate data, reading buffer_size compressed bytes at a time; anything
+    // else is decompressed up front and served from memory
+    fn open_entry_with<'a, T: Read + Seek>(&self, cursor: &'a mut T, codec: &dyn Codec, buffer_size: usize) -> Result<EntryReader<'a, T>> {
+        if self.is_stored() {
+            cursor.seek(SeekFrom::Start(self.offset as u64))?;
+            return Ok(EntryReader::stored(cursor, self.length as u64));
+        }
+        if !codec.is_deflate() {
+            let data = self.read_entry(cursor, codec)?;
+            return Ok(EntryReader::decoded(cursor, data));
+        }
+
+        cursor.seek(SeekFrom::Start(self.offset as u64))?;
+        Ok(EntryReader::new(cursor, self.compressed_length as u64, self.length as u64, buffer_size))
     }
 }
 
 impl PackageHeader {
-    fn read_package_header<K: Read>(cursor: &mut K) -> std::io::Result<PackageHeader> {
+    fn read_package_header<K: Read>(cursor: &mut K, format: &FormatSpec) -> Result<PackageHeader> {
         let mut result = PackageHeader {
             magic: 0,
             toc_offset: 0,
             toc_length: 0,
             version: 0
         };
-        let mut buffer: [u8; 16] = [0; 16];
+        let mut buffer: [u8; ADAT_HEADER_SIZE as usize] = [0; ADAT_HEADER_SIZE as usize];
         cursor.read_exact(&mut buffer)?;
 
         // check magic
-        if &buffer[0..4] != ADAT_MAGIC {
-            return Err(Error::new(ErrorKind::Other,
-                format!("ADAT magic mismatch, found: {:?}", &buffer[0..4])
-            ));
+        if buffer[0..4] != format.magic {
+            let mut found: [u8; 4] = [0; 4];
+            found.copy_from_slice(&buffer[0..4]);
+            return Err(AdatError::MagicMismatch { found });
         }
 
         result.magic = u32le_from_slice(&buffer[0..4]);
@@ -80,19 +393,32 @@ impl PackageHeader {
         result.toc_length = u32le_from_slice(&buffer[8..12]);
         result.version = u32le_from_slice(&buffer[12..16]);
 
-        if result.version != 9 {
-            return Err(Error::new(ErrorKind::Other,
-                format!("ADAT version mismatch, expected 9, found: {}", result.version)
-            ));
+        if !format.versions.contains(&result.version) {
+            return Err(AdatError::UnsupportedVersion(result.version));
         }
 
         Ok(result)
     }
+
+    fn to_bytes(&self) -> [u8; ADAT_HEADER_SIZE as usize] {
+        let mut buffer: [u8; ADAT_HEADER_SIZE as usize] = [0; ADAT_HEADER_SIZE as usize];
+
+        buffer[0..4].copy_from_slice(&self.magic.to_le_bytes());
+        buffer[4..8].copy_from_slice(&self.toc_offset.to_le_bytes());
+        buffer[8..12].copy_from_slice(&self.toc_length.to_le_bytes());
+        buffer[12..16].copy_from_slice(&self.version.to_le_bytes());
+        buffer
+    }
+
+    fn write_package_header<K: Write>(&self, cursor: &mut K) -> std::io::Result<()> {
+        cursor.write_all(&self.to_bytes())
+    }
 }
 
 impl PackageEntry {
-    fn read_package_entry<'b, K: Read>(cursor: &'b mut K) -> std::io::Result<PackageEntry> {
+    fn read_package_entry<K: Read>(cursor: &mut K) -> Result<PackageEntry> {
         let mut entry: PackageEntry = PackageEntry {
+            path: String::new(),
             name: [0; 128],
             offset: 0,
             length: 0,
@@ -101,11 +427,13 @@ impl PackageEntry {
         };
 
         cursor.read_exact(&mut entry.name)?;
+        entry.path = entry.display_name().into_owned();
 
         let mut buffer: [u8; 16] = [0; 16];
         cursor.read_exact(&mut buffer)?;
 
         entry.offset = u32le_from_slice(&buffer[0..4]);
+        // lossless on 32 and 64-bit targets; Toc::check_entry rejects what cannot be allocated
         entry.length = u32le_from_slice(&buffer[4..8]) as usize;
         entry.compressed_length = u32le_from_slice(&buffer[8..12]) as usize;
         entry.u0 = u32le_from_slice(&buffer[12..16]);
@@ -113,7 +441,7 @@ impl PackageEntry {
         Ok(entry)
     }
 
-    fn read_package_entries<'b, K: Read>(cursor: &'b mut K, entry_count: u32) -> std::io::Result<Vec<PackageEntry>> {
+    fn read_package_entries<K: Read>(cursor: &mut K, entry_count: u32) -> Result<Vec<PackageEntry>> {
         let mut entries: Vec<PackageEntry> = Vec::with_capacity(entry_count as usize);
 
         for _ in 0..entry_count {
@@ -122,57 +450,313 @@ impl PackageEntry {
 
         Ok(entries)
     }
+
+    fn write_package_entry<K: Write>(&self, cursor: &mut K) -> std::io::Result<()> {
+        cursor.write_all(&self.name)?;
+
+        let mut buffer: [u8; 16] = [0; 16];
+
+        buffer[0..4].copy_from_slice(&self.offset.to_le_bytes());
+        buffer[4..8].copy_from_slice(&(self.length as u32).to_le_bytes());
+        buffer[8..12].copy_from_slice(&(self.compressed_length as u32).to_le_bytes());
+        buffer[12..16].copy_from_slice(&self.u0.to_le_bytes());
+
+        cursor.write_all(&buffer)
+    }
 }
 
 impl<'b, T: Read + Seek>  Package<'b, T> {
-    pub fn mount_from_cursor(cursor: &'b mut T) -> std::io::Result<Self> {
+    /// Reads and checks only the header: the magic, a supported version and
+    /// a TOC that fits in the file. The TOC itself is not read, so this stays
+    /// cheap no matter how many entries the archive has.
+    pub fn probe_header(cursor: &mut T) -> Result<HeaderInfo> {
+        let options = MountOptions::default().allow_empty(true);
+        let file_len = cursor.seek(SeekFrom::End(0))?;
         cursor.seek(SeekFrom::Start(0))?;
 
-        let header: PackageHeader = PackageHeader::read_package_header(cursor)?;
-        let entry_count = header.toc_length / ADAT_ENTRY_SIZE;
+        let header = PackageHeader::read_package_header(cursor, &options.format)?;
+        Toc::check_header(&header, file_len, &options)?;
+
+        Ok(HeaderInfo {
+            magic: header.magic.to_le_bytes(),
+            version: header.version,
+            toc_offset: header.toc_offset,
+            toc_length: header.toc_length
+        })
+    }
+
+    pub fn mount_from_cursor(cursor: &'b mut T) -> Result<Self> {
+        Package::mount_with_options(cursor, &MountOptions::default())
+    }
+
+    pub fn mount_with_options(cursor: &'b mut T, options: &MountOptions) -> Result<Self> {
+        Package::mount_package_cursor(PackageCursor::Borrowed(cursor), options)
+    }
+
+    /// Mounts an archive of a format with the ADAT layout under another
+    /// magic or version, with otherwise default options.
+    pub fn mount_with_format(cursor: &'b mut T, format: FormatSpec) -> Result<Self> {
+        Package::mount_with_options(cursor, &MountOptions::new().format(format))
+    }
+
+    /// Mounts with the default options under `limits`.
+    pub fn mount_with_limits(cursor: &'b mut T, limits: MountLimits) -> Result<Self> {
+        Package::mount_with_options(cursor, &MountOptions::new().limits(limits))
+    }
+
+    /// Like `mount_with_options`, but entries that fail the checks of
+    /// `options` (a name that is not UTF-8 under `strict_names`, data past
+    /// the end of the file, a rejected duplicate) are left out of the TOC
+    /// instead of failing the whole mount, and returned along with the package.
+    ///
+    /// Still fails if the header or the TOC itself is unreadable, or if no
+    /// entry is left.
+    pub fn mount_lenient(cursor: &'b mut T, options: &MountOptions) -> Result<(Self, Vec<RejectedEntry>)> {
+        let (toc, rejected) = Toc::read_toc_with(cursor, options, true)?;
+        Ok((Package::with_toc(PackageCursor::Borrowed(cursor), toc, options), rejected))
+    }
+
+    fn mount_package_cursor(mut package_cursor: PackageCursor<'b, T>, options: &MountOptions) -> Result<Self> {
+        let toc = Toc::read_toc(package_cursor.get_mut(), options)?;
+        Ok(Package::with_toc(package_cursor, toc, options))
+    }
 
-        if entry_count == 0 {
-            return Err(Error::new(ErrorKind::Other, "empty toc"));
+    fn with_toc(cursor: PackageCursor<'b, T>, toc: Toc, options: &MountOptions) -> Self {
+        Package {
+            cursor,
+            toc: Arc::new(toc),
+            max_decompressed_size: options.limits.max_decompressed_size,
+            cache: EntryCache::default(),
+            scratch: Vec::new()
         }
+    }
+
+    /// Caps the decompressed size of entries that may be read, no matter
+    /// what length the TOC declares for them.
+    ///
+    /// Every other reader type has the same method, with the same meaning.
+    pub fn set_max_decompressed_size(&mut self, limit: usize) {
+        self.max_decompressed_size = Some(limit);
+    }
+
+    pub fn max_decompressed_size(&self) -> Option<usize> {
+        self.max_decompressed_size
+    }
+
+    // looks up an entry that may be read under the current limits, along with
+    // the codec and the cursor to read it with
+    fn readable_entry(&mut self, entry_path: &str) -> Result<(&PackageEntry, &dyn Codec, &mut T)> {
+        let position = self.toc.position(entry_path)?;
+        self.readable_entry_at(position)
+    }
+
+    fn readable_entry_at(&mut self, position: usize) -> Result<(&PackageEntry, &dyn Codec, &mut T)> {
+        let pe = self.toc.readable_entry_at(position, self.max_decompressed_size)?;
+        Ok((pe, &*self.toc.codec, self.cursor.get_mut()))
+    }
+
+    // decompresses the entry at position into out, reading its compressed
+    // data into the package's scratch buffer
+    fn read_entry_at_into(&mut self, position: usize, out: &mut Vec<u8>) -> Result<usize> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        let result = self.readable_entry_at(position)
+            .and_then(|(pe, codec, cursor)| pe.read_entry_with(cursor, codec, &mut scratch, out));
+        self.scratch = scratch;
+        result
+    }
+
+    /// Reads the entry at the given TOC position; this also reaches entries
+    /// shadowed by a later duplicate of their name.
+    pub fn read_entry_by_index(&mut self, index: usize) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::new();
+        self.read_entry_at_into(index, &mut data)?;
+        Ok(data)
+    }
 
-        cursor.seek(SeekFrom::Start(header.toc_offset as u64))?;
-        let entries = PackageEntry::read_package_entries(cursor, entry_count)?;
+    pub fn read_entry(&mut self, entry_path: &str) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::new();
+        self.read_entry_into(entry_path, &mut data)?;
+        Ok(data)
+    }
 
-        let mut entrymap: HashMap<String, PackageEntry> = HashMap::with_capacity(entries.len());
-        for entry in entries {
-            let path = entry.get_name().map_err(|e| {
-                Error::new(ErrorKind::Other, e)
-            })?;
-            entrymap.insert(path.to_string(), entry);
+    /// Reads several entries, keyed by the names they were requested by.
+    ///
+    /// All names are resolved before anything is read, so a missing one fails
+    /// with `EntryNotFound` naming it; the entries are then read in offset
+    /// order to keep seeking to a minimum.
+    pub fn read_entries(&mut self, entry_paths: &[&str]) -> Result<HashMap<String, Vec<u8>>> {
+        let mut positions: Vec<(usize, &str)> = Vec::with_capacity(entry_paths.len());
+        for &entry_path in entry_paths {
+            positions.push((self.toc.position(entry_path)?, entry_path));
         }
+        positions.sort_by_key(|&(position, _)| self.toc.entries[position].offset);
 
-        let result = Package {
-            cursor: cursor,
-            header: header,
-            entries: entrymap
-        };
+        let mut entries: HashMap<String, Vec<u8>> = HashMap::with_capacity(positions.len());
+        for (position, entry_path) in positions {
+            let data = self.read_entry_by_index(position)?;
+            entries.insert(entry_path.to_string(), data);
+        }
 
-        Ok(result)
+        Ok(entries)
     }
 
-    pub fn list_entries(&self) -> Vec<String> {
-        self.entries.keys().map(|k| k.to_string()).collect()
+    /// Like `read_entry`, but decompresses into `out`, reusing its allocation.
+    ///
+    /// `out` is cleared first; returns the number of bytes written to it.
+    /// The compressed data is read into a buffer the package keeps, so
+    /// reading many entries this way allocates next to nothing; see
+    /// `release_scratch` to give that buffer back.
+    pub fn read_entry_into(&mut self, entry_path: &str, out: &mut Vec<u8>) -> Result<usize> {
+        let position = self.toc.position(entry_path)?;
+        self.read_entry_at_into(position, out)
     }
 
-    pub fn read_entry(&mut self, entry_path: &str) -> std::io::Result<Vec<u8>> {
-        self.entries.get(entry_path).ok_or(Error::new(
-            ErrorKind::Other, "entry not found"
-        )).and_then(|pe| {
-            pe.read_entry(self.cursor)
-        })
+    /// Frees the buffer reads keep for compressed data, which is as large as
+    /// the largest entry read so far.
+    pub fn release_scratch(&mut self) {
+        self.scratch = Vec::new();
     }
 
-    pub fn read_text_entry(&mut self, entry_path: &str) -> std::io::Result<String> {
-        self.read_entry(entry_path).and_then(|v| {
-            String::from_utf8(v).map_err(|e| {
-                Error::new(ErrorKind::Other, e)
-            })
-        })
+    /// Reads the compressed bytes of an entry exactly as stored, without inflating them.
+    pub fn read_entry_raw(&mut self, entry_path: &str) -> Result<Vec<u8>> {
+        let pe = self.toc.lookup(entry_path)?;
+        pe.read_entry_raw(self.cursor.get_mut())
+    }
+
+    /// Streams the compressed bytes of an entry as stored, bounded to its compressed length.
+    pub fn open_entry_raw(&mut self, entry_path: &str) -> Result<Take<&mut T>> {
+        let pe = self.toc.lookup(entry_path)?;
+        let cursor = self.cursor.get_mut();
+
+        cursor.seek(SeekFrom::Start(pe.offset as u64))?;
+        Ok(cursor.take(pe.compressed_length as u64))
+    }
+
+    /// Opens a streaming reader over the decompressed contents of an entry.
+    pub fn open_entry(&mut self, entry_path: &str) -> Result<EntryReader<'_, T>> {
+        let (pe, codec, cursor) = self.readable_entry(entry_path)?;
+        pe.open_entry(cursor, codec)
+    }
+
+    /// Opens a reader over the decompressed contents of an entry that can
+    /// also seek; backward seeks in compressed entries decompress from the
+    /// start again, see `SeekableEntryReader`.
+    pub fn open_entry_seekable(&mut self, entry_path: &str) -> Result<SeekableEntryReader<'_, T>> {
+        let (pe, codec, cursor) = self.readable_entry(entry_path)?;
+        pe.open_entry_seekable(cursor, codec)
+    }
+
+    /// Decompresses the `len` bytes of an entry starting at `start`, or
+    /// fewer if the entry ends before that.
+    ///
+    /// The entry is streamed: bytes before the window are decompressed and
+    /// dropped, and decompression stops as soon as the window is filled.
+    pub fn read_entry_range(&mut self, entry_path: &str, start: u64, len: u64) -> Result<Vec<u8>> {
+        let (pe, codec, cursor) = self.readable_entry(entry_path)?;
+        let name = pe.path.clone();
+        let window = len.min((pe.length as u64).saturating_sub(start));
+        let mut reader = pe.open_entry(cursor, codec)?;
+
+        let mut data: Vec<u8> = Vec::with_capacity(window as usize);
+        std::io::copy(&mut (&mut reader).take(start), &mut std::io::sink())
+            .and_then(|_| reader.take(len).read_to_end(&mut data))
+            .map_err(|e| AdatError::from_stream(e).with_entry(&name))?;
+
+        Ok(data)
+    }
+
+    /// Streams the decompressed contents of an entry into `out`, without
+    /// holding all of it in memory; returns the number of bytes written.
+    pub fn read_entry_to_writer<W: Write + ?Sized>(&mut self, entry_path: &str, out: &mut W) -> Result<u64> {
+        let (pe, codec, cursor) = self.readable_entry(entry_path)?;
+        pe.copy_entry_to(cursor, codec, out)
+    }
+
+    /// Like `read_entry_to_writer`, but with the memory used on the way
+    /// capped: compressed data is read and decompressed data written `window`
+    /// bytes at a time, so apart from the inflate state of about 40 KiB only
+    /// twice `window` is held, however large the entry.
+    ///
+    /// Entries of codecs other than deflate are still decompressed whole first.
+    pub fn read_entry_to_writer_bounded<W: Write + ?Sized>(&mut self, entry_path: &str, out: &mut W, window: usize) -> Result<u64> {
+        let (pe, codec, cursor) = self.readable_entry(entry_path)?;
+        let mut reader = pe.open_entry_with(cursor, codec, window)?;
+        let mut chunk: Vec<u8> = vec![0; window.max(1)];
+        let mut written: u64 = 0;
+
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => return Ok(written),
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(AdatError::from_stream(e).with_entry(&pe.path))
+            };
+            out.write_all(&chunk[..read]).map_err(|e| AdatError::from(e).with_entry(&pe.path))?;
+            written += read as u64;
+        }
+    }
+
+    /// Decompresses every TOC entry, shadowed duplicates included, and
+    /// collects each one that fails along with its error.
+    ///
+    /// Entries have to decompress to exactly their declared length.
+    pub fn verify(&mut self) -> std::result::Result<(), Vec<(String, AdatError)>> {
+        let mut failures: Vec<(String, AdatError)> = Vec::new();
+        let mut scratch: Vec<u8> = Vec::new();
+
+        for position in 0..self.toc.entries.len() {
+            let result = self.readable_entry_at(position).and_then(|(pe, codec, cursor)| {
+                pe.read_entry_into(cursor, codec, &mut scratch).map(|_| ())
+            });
+
+            if let Err(e) = result {
+                failures.push((self.toc.entries[position].path.clone(), e));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Reads an entry as UTF-8 text. The decompressed buffer becomes the
+    /// `String` as is, without a copy.
+    pub fn read_text_entry(&mut self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).and_then(text_from_bytes)
+    }
+
+    /// Like `read_text_entry`, but replaces invalid UTF-8 sequences with U+FFFD.
+    ///
+    /// Only text with invalid sequences is copied, to replace them.
+    pub fn read_text_entry_lossy(&mut self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).map(text_from_bytes_lossy)
+    }
+}
+
+impl<T: Read + Seek> Deref for Package<'_, T> {
+    type Target = Toc;
+
+    fn deref(&self) -> &Toc {
+        &self.toc
+    }
+}
+
+impl<'a, T: Read + Seek> IntoIterator for &'a Package<'_, T> {
+    type Item = &'a str;
+    type IntoIter = EntryNames<'a>;
+
+    fn into_iter(self) -> EntryNames<'a> {
+        self.entry_names()
+    }
+}
+
+impl<'b> TryFrom<&'b mut std::fs::File> for Package<'b, std::fs::File> {
+    type Error = AdatError;
+
+    fn try_from(file: &'b mut std::fs::File) -> Result<Self> {
+        Package::mount_from_cursor(file)
     }
 }
 
@@ -180,17 +764,25 @@ impl<'b, T: Read + Seek>  Package<'b, T> {
 mod tests {
     use super::*;
     use std::fs::File;
+    use std::io::Cursor;
 
     #[test]
     fn it_works() {
         let mut file = File::open("TEST.dat").unwrap();
         let mut result = Package::mount_from_cursor(&mut file).unwrap();
 
-        assert_eq!(result.header.magic, 0x54414441); // ADAT
-        assert_eq!(result.header.version, 9); // expected
+        assert_eq!(result.toc.header.magic, 0x54414441); // ADAT
+        assert_eq!(result.toc.header.version, 9); // expected
+        assert_eq!(result.version(), 9);
+        assert_eq!(result.toc_offset(), 16);
+        assert_eq!(result.toc_length(), ADAT_ENTRY_SIZE);
 
         let names = result.list_entries();
         assert_eq!(names.len(), 1);
+        assert_eq!(result.len(), 1);
+        assert!(!result.is_empty());
+        assert!(result.contains("some/path/foo.txt"));
+        assert!(!result.contains("some/path"));
 
         assert_eq!(names[0], "some/path/foo.txt");
 
@@ -198,9 +790,738 @@ mod tests {
         for idx in 1..6 {
             expected_content = format!("{}\n{} {}", expected_content, idx, "hello world from a test file!");
         }
-        expected_content = expected_content + "\n";
+        expected_content += "\n";
         assert_eq!(result.read_text_entry("some/path/foo.txt").unwrap(), expected_content);
 
         drop(file);
     }
+
+    #[test]
+    fn distinguishes_errors() {
+        let mut not_adat = Cursor::new(b"ZDAT\x10\0\0\0\0\0\0\0\x09\0\0\0".to_vec());
+        assert!(matches!(
+            Package::mount_from_cursor(&mut not_adat),
+            Err(AdatError::MagicMismatch { found }) if &found == b"ZDAT"
+        ));
+
+        assert!(!is_adat(not_adat.get_ref()));
+        assert!(is_adat(&std::fs::read("TEST.dat").unwrap()));
+        assert!(!is_adat(b"ADA"));
+
+        let mut version_8 = Cursor::new(b"ADAT\x10\0\0\0\0\0\0\0\x08\0\0\0".to_vec());
+        assert!(matches!(Package::mount_from_cursor(&mut version_8), Err(AdatError::UnsupportedVersion(8))));
+
+        let mut empty = Cursor::new(b"ADAT\x10\0\0\0\0\0\0\0\x09\0\0\0".to_vec());
+        assert!(matches!(Package::mount_from_cursor(&mut empty), Err(AdatError::EmptyToc)));
+        let package = Package::mount_with_options(&mut empty, &MountOptions::new().allow_empty(true)).unwrap();
+        assert!(package.is_empty());
+        assert_eq!(package.list_entries(), Vec::<String>::new());
+
+        let mut misaligned = Cursor::new(b"ADAT\x10\0\0\0\x91\0\0\0\x09\0\0\0".to_vec());
+        assert!(matches!(Package::mount_from_cursor(&mut misaligned), Err(AdatError::TocMisaligned(145))));
+
+        // claims ~29 million entries in a 16 byte file
+        let huge_toc = [b"ADAT\x10\0\0\0".as_slice(), &(ADAT_ENTRY_SIZE * 29_826_161).to_le_bytes(), b"\x09\0\0\0"].concat();
+        assert!(matches!(
+            Package::mount_from_cursor(&mut Cursor::new(huge_toc)),
+            Err(AdatError::TocOutOfBounds { toc_offset: 16, file_len: 16, .. })
+        ));
+
+        let mut file = File::open("TEST.dat").unwrap();
+        let mut package = Package::mount_from_cursor(&mut file).unwrap();
+        assert!(matches!(package.read_entry("missing.txt"), Err(AdatError::EntryNotFound(name)) if name == "missing.txt"));
+    }
+
+    #[test]
+    fn checks_sizes_against_address_space() {
+        assert!(fits_in_memory(0));
+        assert!(fits_in_memory(isize::MAX as u64));
+        assert!(!fits_in_memory(isize::MAX as u64 + 1));
+        assert!(!fits_in_memory(u64::MAX));
+        #[cfg(target_pointer_width = "32")]
+        assert!(!fits_in_memory(u32::MAX as u64));
+        #[cfg(target_pointer_width = "64")]
+        assert!(fits_in_memory(u32::MAX as u64));
+    }
+
+    #[test]
+    fn mounts_format_variants() {
+        let mut data = package_with(&[("a.txt", b"variant")]).into_inner();
+        data[0..4].copy_from_slice(b"XDAT");
+        data[12..16].copy_from_slice(&3u32.to_le_bytes());
+        let mut cursor = Cursor::new(data);
+
+        assert!(matches!(Package::mount_from_cursor(&mut cursor), Err(AdatError::MagicMismatch { found }) if &found == b"XDAT"));
+        let wrong_version = FormatSpec { magic: *b"XDAT", ..FormatSpec::default() };
+        assert!(matches!(Package::mount_with_format(&mut cursor, wrong_version), Err(AdatError::UnsupportedVersion(3))));
+
+        let mut package = Package::mount_with_format(&mut cursor, FormatSpec { magic: *b"XDAT", versions: vec![3] }).unwrap();
+        assert_eq!(package.read_text_entry("a.txt").unwrap(), "variant");
+        assert_eq!(FormatSpec::default(), FormatSpec { magic: MAGIC, versions: vec![9] });
+    }
+
+    #[test]
+    fn probes_headers() {
+        let mut file = File::open("TEST.dat").unwrap();
+        let info = Package::probe_header(&mut file).unwrap();
+        assert_eq!(info, HeaderInfo { magic: MAGIC, version: 9, toc_offset: 16, toc_length: ADAT_ENTRY_SIZE });
+
+        let mut empty = Cursor::new(b"ADAT\x10\0\0\0\0\0\0\0\x09\0\0\0".to_vec());
+        assert_eq!(Package::probe_header(&mut empty).unwrap().toc_length, 0);
+
+        let mut truncated = Cursor::new(b"ADAT\x10\0\0\0\x90\0\0\0\x09\0\0\0".to_vec());
+        assert!(matches!(Package::probe_header(&mut truncated), Err(AdatError::TocOutOfBounds { .. })));
+        let mut version_8 = Cursor::new(b"ADAT\x10\0\0\0\0\0\0\0\x08\0\0\0".to_vec());
+        assert!(matches!(Package::probe_header(&mut version_8), Err(AdatError::UnsupportedVersion(8))));
+    }
+
+    #[test]
+    fn encodes_header_bytes() {
+        let data = std::fs::read("TEST.dat").unwrap();
+        let mut file = File::open("TEST.dat").unwrap();
+        let package = Package::mount_from_cursor(&mut file).unwrap();
+        assert_eq!(package.header_bytes(), data[..16]);
+
+        let mut cursor = package_with(&[("a.txt", b"a")]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let header = package.header_bytes();
+        assert_eq!(&header[..4], b"ADAT");
+        drop(package);
+        assert_eq!(header, cursor.get_ref()[..16]);
+    }
+
+    #[test]
+    fn rejects_misaligned_toc_of_real_archive() {
+        // one byte more than the single entry, with the file long enough for it
+        let mut data = std::fs::read("TEST.dat").unwrap();
+        data[8..12].copy_from_slice(&(ADAT_ENTRY_SIZE + 1).to_le_bytes());
+        data.push(0);
+
+        let expected = ADAT_ENTRY_SIZE + 1;
+        assert!(matches!(Package::mount_from_cursor(&mut Cursor::new(&data)), Err(AdatError::TocMisaligned(n)) if n == expected));
+        assert!(matches!(SlicePackage::mount_from_bytes(&data), Err(AdatError::TocMisaligned(n)) if n == expected));
+    }
+
+    #[test]
+    fn rejects_entry_past_end_of_file() {
+        let mut data = std::fs::read("TEST.dat").unwrap();
+        let file_len = data.len() as u64;
+        let compressed_length_at = (ADAT_HEADER_SIZE + 128 + 8) as usize;
+        data[compressed_length_at..compressed_length_at + 4].copy_from_slice(&1000u32.to_le_bytes());
+
+        let mut cursor = Cursor::new(data);
+        match Package::mount_from_cursor(&mut cursor) {
+            Err(AdatError::EntryOutOfBounds { name, compressed_length, file_len: len, .. }) => {
+                assert_eq!(name, "some/path/foo.txt");
+                assert_eq!(compressed_length, 1000);
+                assert_eq!(len, file_len);
+            }
+            other => panic!("unexpected mount result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn limits_decompressed_size() {
+        let mut file = File::open("TEST.dat").unwrap();
+        let mut package = Package::mount_from_cursor(&mut file).unwrap();
+
+        package.set_max_decompressed_size(160);
+        assert!(matches!(
+            package.read_entry("some/path/foo.txt"),
+            Err(AdatError::EntryTooLarge { length: 161, limit: 160, .. })
+        ));
+        assert!(package.open_entry("some/path/foo.txt").is_err());
+
+        package.set_max_decompressed_size(161);
+        assert_eq!(package.read_entry("some/path/foo.txt").unwrap().len(), 161);
+    }
+
+    #[test]
+    fn reads_raw_compressed_bytes() {
+        let mut file = File::open("TEST.dat").unwrap();
+        let mut package = Package::mount_from_cursor(&mut file).unwrap();
+
+        let raw = package.read_entry_raw("some/path/foo.txt").unwrap();
+        assert_eq!(raw.len() as u32, package.entry_info("some/path/foo.txt").unwrap().compressed_length);
+        assert_eq!(raw[0], 0x78); // zlib header
+        assert_eq!(
+            miniz_oxide::inflate::decompress_to_vec_zlib(&raw).unwrap(),
+            package.read_entry("some/path/foo.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn streams_raw_compressed_bytes() {
+        let mut cursor = package_with(&[("a.bin", &[4; 5000]), ("b.txt", b"next")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let expected = package.read_entry_raw("a.bin").unwrap();
+
+        let mut streamed = Vec::new();
+        package.open_entry_raw("a.bin").unwrap().read_to_end(&mut streamed).unwrap();
+        assert_eq!(streamed, expected);
+        assert!(matches!(package.open_entry_raw("missing"), Err(AdatError::EntryNotFound(_))));
+    }
+
+    #[test]
+    fn reads_entry_ranges() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let mut cursor = package_with(&[("big.bin", &data), ("short.txt", b"stored")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert_eq!(package.read_entry_range("big.bin", 0, 16).unwrap(), &data[..16]);
+        assert_eq!(package.read_entry_range("big.bin", 70_000, 5000).unwrap(), &data[70_000..75_000]);
+        assert_eq!(package.read_entry_range("big.bin", 99_990, 100).unwrap(), &data[99_990..]);
+        assert!(package.read_entry_range("big.bin", 200_000, 10).unwrap().is_empty());
+        assert_eq!(package.read_entry_range("short.txt", 2, 3).unwrap(), b"ore");
+        assert!(matches!(package.read_entry_range("missing", 0, 1), Err(AdatError::EntryNotFound(_))));
+
+        package.set_max_decompressed_size(1000);
+        assert!(matches!(package.read_entry_range("big.bin", 0, 1), Err(AdatError::EntryTooLarge { .. })));
+    }
+
+    #[test]
+    fn streams_within_a_window() {
+        // records the largest write, to show output comes in window sized chunks
+        struct Chunks(Vec<u8>, usize);
+        impl Write for Chunks {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.1 = self.1.max(buf.len());
+                self.0.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut cursor = package_with(&[("big.bin", &data), ("tiny.txt", b"tiny")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        for window in [1, 64, 100_000] {
+            let mut out = Chunks(Vec::new(), 0);
+            assert_eq!(package.read_entry_to_writer_bounded("big.bin", &mut out, window).unwrap(), 200_000);
+            assert_eq!(out.0, data);
+            assert!(out.1 <= window);
+        }
+
+        let mut out: Vec<u8> = Vec::new();
+        assert_eq!(package.read_entry_to_writer_bounded("tiny.txt", &mut out, 3).unwrap(), 4);
+        assert_eq!(out, b"tiny");
+        assert!(matches!(package.read_entry_to_writer_bounded("missing", &mut out, 3), Err(AdatError::EntryNotFound(_))));
+    }
+
+    #[test]
+    fn reads_entries_in_batch() {
+        let mut cursor = package_with(&[("c.txt", b"third"), ("a.txt", b"first"), ("b.bin", &[2; 3000])]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let entries = package.read_entries(&["b.bin", "c.txt"]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["b.bin"], vec![2; 3000]);
+        assert_eq!(entries["c.txt"], b"third");
+
+        match package.read_entries(&["a.txt", "nope.txt"]) {
+            Err(AdatError::EntryNotFound(name)) => assert_eq!(name, "nope.txt"),
+            other => panic!("expected a missing entry, got {:?}", other)
+        }
+        assert!(package.read_entries(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn streams_entries_into_writers() {
+        let data: Vec<u8> = (0..50_000u32).map(|i| (i % 7) as u8).collect();
+        let mut cursor = package_with(&[("big.bin", &data), ("short.txt", b"stored")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let mut out: Vec<u8> = Vec::new();
+        assert_eq!(package.read_entry_to_writer("big.bin", &mut out).unwrap(), 50_000);
+        assert_eq!(out, data);
+
+        // appends to whatever the writer already holds
+        assert_eq!(package.read_entry_to_writer("short.txt", &mut out).unwrap(), 6);
+        assert_eq!(&out[50_000..], b"stored");
+
+        let mut sink = std::io::sink();
+        assert!(matches!(package.read_entry_to_writer("missing", &mut sink), Err(AdatError::EntryNotFound(_))));
+    }
+
+    #[test]
+    fn accepts_configured_versions() {
+        let mut data = std::fs::read("TEST.dat").unwrap();
+        data[12..16].copy_from_slice(&8u32.to_le_bytes());
+        let mut cursor = Cursor::new(data);
+
+        assert!(matches!(Package::mount_from_cursor(&mut cursor), Err(AdatError::UnsupportedVersion(8))));
+
+        let options = MountOptions::new().accept_versions(&[8, 9]);
+        let mut package = Package::mount_with_options(&mut cursor, &options).unwrap();
+        assert_eq!(package.version(), 8);
+        assert!(package.read_text_entry("some/path/foo.txt").is_ok());
+    }
+
+    #[test]
+    fn mounts_with_limits() {
+        let mut cursor = package_with(&[("a.txt", b"a"), ("long/name.txt", b"b"), ("big.bin", &[0; 5000])]);
+        let limits = MountLimits { max_entries: Some(2), ..MountLimits::default() };
+        assert!(matches!(Package::mount_with_limits(&mut cursor, limits), Err(AdatError::TooManyEntries { count: 3, limit: 2 })));
+
+        // rejected from the header alone
+        let huge_toc = [b"ADAT\x10\0\0\0".as_slice(), &(ADAT_ENTRY_SIZE * 1_000_000).to_le_bytes(), b"\x09\0\0\0"].concat();
+        let limits = MountLimits { max_entries: Some(1000), ..MountLimits::default() };
+        assert!(matches!(Package::mount_with_limits(&mut Cursor::new(huge_toc), limits), Err(AdatError::TooManyEntries { .. })));
+
+        let limits = MountLimits { max_name_len: Some(8), ..MountLimits::default() };
+        match Package::mount_with_limits(&mut cursor, limits) {
+            Err(AdatError::NameOverLimit { name, length: 13, limit: 8 }) => assert_eq!(name, "long/name.txt"),
+            other => panic!("expected a name over the limit, got {:?}", other)
+        }
+
+        let limits = MountLimits { max_entries: Some(3), max_decompressed_size: Some(1000), max_name_len: Some(13) };
+        let mut package = Package::mount_with_limits(&mut cursor, limits).unwrap();
+        assert_eq!(package.max_decompressed_size(), Some(1000));
+        assert!(matches!(package.read_entry("big.bin"), Err(AdatError::EntryTooLarge { .. })));
+        assert_eq!(package.read_entry("a.txt").unwrap(), b"a");
+    }
+
+    #[test]
+    fn mounts_leniently() {
+        let mut data = package_with(&[("good.txt", b"good"), ("bad_.txt", b"bad"), ("far.txt", b"far"), ("goo_.txt", b"again")]).into_inner();
+        let toc_offset = u32le_from_slice(&data[4..8]) as usize;
+        let entry_at = |position: usize| toc_offset + position * ADAT_ENTRY_SIZE as usize;
+        data[entry_at(1) + 3] = 0xE9; // bad\xE9.txt, not UTF-8 any more
+        let compressed_length_at = entry_at(2) + 128 + 8;
+        data[compressed_length_at..compressed_length_at + 4].copy_from_slice(&100_000u32.to_le_bytes());
+        data[entry_at(3) + 3] = b'd'; // a second good.txt
+
+        let options = MountOptions::new().strict_names(true).reject_duplicates(true);
+        let mut cursor = Cursor::new(data);
+        assert!(matches!(Package::mount_with_options(&mut cursor, &options), Err(AdatError::InvalidName(_))));
+
+        let (mut package, rejected) = Package::mount_lenient(&mut cursor, &options).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["good.txt"]);
+        assert_eq!(package.read_text_entry("good.txt").unwrap(), "good");
+
+        let positions: Vec<usize> = rejected.iter().map(|r| r.position).collect();
+        assert_eq!(positions, vec![1, 2, 3]);
+        assert!(matches!(rejected[0].error, AdatError::InvalidName(_)));
+        assert_eq!(rejected[0].info.name_bytes, b"bad\xE9.txt");
+        assert!(matches!(rejected[1].error, AdatError::EntryOutOfBounds { .. }));
+        assert!(matches!(&rejected[2].error, AdatError::DuplicateEntry(name) if name == "good.txt"));
+
+        // only the entry past the end of the file is left without the checks on names
+        let (package, rejected) = Package::mount_lenient(&mut cursor, &MountOptions::new().strict_names(false)).unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(package.len(), 2);
+    }
+
+    #[test]
+    fn rejects_toc_overlap() {
+        let mut data = package_with(&[("a.txt", b"first"), ("b.txt", b"second")]).into_inner();
+        let toc_offset = u32le_from_slice(&data[4..8]);
+        let offset_at = toc_offset as usize + ADAT_ENTRY_SIZE as usize + 128;
+        data[offset_at..offset_at + 4].copy_from_slice(&(toc_offset - 2).to_le_bytes());
+
+        let options = MountOptions::new().reject_toc_overlap(true);
+        let mut cursor = Cursor::new(data);
+        assert!(Package::mount_from_cursor(&mut cursor).is_ok());
+        assert!(matches!(
+            Package::mount_with_options(&mut cursor, &options),
+            Err(AdatError::EntryOverlapsToc { name, offset, .. }) if name == "b.txt" && offset == toc_offset - 2
+        ));
+
+        let (package, rejected) = Package::mount_lenient(&mut cursor, &options).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["a.txt"]);
+        assert_eq!(rejected[0].position, 1);
+
+        // entries sharing their data are fine
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.deduplicate(true);
+        writer.add_entry("a.bin", &[1; 1000]).unwrap();
+        writer.add_entry("b.bin", &[1; 1000]).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        assert_eq!(Package::mount_with_options(&mut cursor, &options).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn trims_names_at_first_null() {
+        let long_name = "n".repeat(128);
+        let mut data = package_with(&[("foo_bar.txt", b"embedded"), (&long_name, b"unpadded")]).into_inner();
+        let toc_offset = u32le_from_slice(&data[4..8]) as usize;
+        data[toc_offset + 3] = 0; // foo\0bar.txt
+
+        let mut cursor = Cursor::new(data);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["foo", long_name.as_str()]);
+        assert!(!package.contains("foo_bar.txt") && !package.contains("foobar.txt"));
+        assert_eq!(package.read_text_entry("foo").unwrap(), "embedded");
+        assert_eq!(package.entry_info("foo").unwrap().name_bytes, b"foo");
+        assert_eq!(package.read_text_entry(&long_name).unwrap(), "unpadded");
+        assert_eq!(package.entry_info(&long_name).unwrap().name_bytes.len(), 128);
+        assert_eq!(package.unterminated_names(), vec![long_name.as_str()]);
+        drop(package);
+
+        let options = MountOptions::new().reject_unterminated_names(true);
+        assert!(matches!(Package::mount_with_options(&mut cursor, &options), Err(AdatError::UnterminatedName(name)) if name == long_name));
+        let (package, rejected) = Package::mount_lenient(&mut cursor, &options).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["foo"]);
+        assert!(package.unterminated_names().is_empty());
+        assert_eq!((rejected[0].position, rejected[0].info.name_bytes.len()), (1, 128));
+    }
+
+    #[test]
+    fn mounts_non_utf8_names() {
+        let mut data = std::fs::read("TEST.dat").unwrap();
+        let name_at = ADAT_HEADER_SIZE as usize;
+        data[name_at + 11] = 0xE9; // latin-1 é instead of the first o in foo.txt
+        let mut cursor = Cursor::new(data);
+
+        let options = MountOptions::new().strict_names(true);
+        assert!(matches!(Package::mount_with_options(&mut cursor, &options), Err(AdatError::InvalidName(_))));
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let name = package.find_by_name_bytes(b"some/path/f\xE9o.txt").unwrap().to_string();
+        assert_eq!(name, "some/path/f\u{FFFD}o.txt");
+        assert_eq!(package.list_entries(), vec![name.clone()]);
+        assert_eq!(package.entry_info(&name).unwrap().name_bytes, b"some/path/f\xE9o.txt");
+        assert!(package.read_text_entry(&name).unwrap().contains("hello world"));
+    }
+
+    #[test]
+    fn reads_malformed_text_lossily() {
+        let mut cursor = package_with(&[("latin1.txt", b"caf\xE9")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert!(matches!(package.read_text_entry("latin1.txt"), Err(AdatError::InvalidText(_))));
+        assert_eq!(package.read_text_entry_lossy("latin1.txt").unwrap(), "caf\u{FFFD}");
+    }
+
+    #[test]
+    fn keeps_duplicate_entries() {
+        let mut data = package_with(&[("a.txt", b"first"), ("b.txt", b"other"), ("c.txt", b"second")]).into_inner();
+        let toc_offset = u32le_from_slice(&data[4..8]) as usize;
+        let third_name_at = toc_offset + 2 * ADAT_ENTRY_SIZE as usize;
+        data[third_name_at] = b'a'; // rename c.txt to a.txt
+        let mut cursor = Cursor::new(data);
+
+        let options = MountOptions::new().reject_duplicates(true);
+        assert!(matches!(
+            Package::mount_with_options(&mut cursor, &options),
+            Err(AdatError::DuplicateEntry(name)) if name == "a.txt"
+        ));
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.len(), 2);
+        assert_eq!(package.duplicate_names(), vec!["a.txt"]);
+        assert_eq!(package.entries_named("a.txt").len(), 2);
+        assert_eq!(package.read_text_entry("a.txt").unwrap(), "second");
+        assert_eq!(package.read_text_entry("b.txt").unwrap(), "other");
+    }
+
+    #[test]
+    fn preserves_toc_order() {
+        let names = ["z.txt", "a.txt", "m/n.txt", "b.txt"];
+        let mut cursor = package_with(&names.map(|name| (name, name.as_bytes())));
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), names);
+    }
+
+    #[test]
+    fn reads_by_index() {
+        let mut cursor = package_with(&[("a.txt", b"first"), ("b.txt", b"second")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert_eq!(package.name_at(1), Some("b.txt"));
+        assert_eq!(package.name_at(2), None);
+        assert_eq!(package.read_entry_by_index(0).unwrap(), b"first");
+        assert!(matches!(package.read_entry_by_index(2), Err(AdatError::IndexOutOfBounds { index: 2, len: 2 })));
+    }
+
+    #[test]
+    fn entry_metadata() {
+        let mut file = File::open("TEST.dat").unwrap();
+        let package = Package::mount_from_cursor(&mut file).unwrap();
+
+        let info = package.entry_info("some/path/foo.txt").unwrap();
+        assert_eq!(info.name, "some/path/foo.txt");
+        assert_eq!(info.offset, 16 + ADAT_ENTRY_SIZE);
+        assert_eq!(info.length, 5 * 32 + 1);
+        assert!(info.compressed_length < info.length);
+        assert_eq!(info.u0, 0);
+
+        assert_eq!(package.entries_info(), vec![info.clone()]);
+        assert_eq!(package.entry_names().collect::<Vec<_>>(), vec!["some/path/foo.txt"]);
+        for name in &package {
+            assert_eq!(name, "some/path/foo.txt");
+        }
+        assert_eq!(package.iter().collect::<Vec<_>>(), vec![("some/path/foo.txt", info)]);
+        assert_eq!(package.entry_info("missing.txt"), None);
+    }
+
+    #[test]
+    fn lists_manifest() {
+        let mut cursor = package_with(&[("b.txt", b"b"), ("dir/a.txt", b"aa")]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let manifest = package.manifest();
+        assert_eq!(manifest.version, 9);
+        let names: Vec<&str> = manifest.entries.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, vec!["b.txt", "dir/a.txt"]);
+        assert_eq!(manifest.entries[1], package.entry_info("dir/a.txt").unwrap());
+    }
+
+    #[test]
+    fn sums_sizes() {
+        let mut file = File::open("TEST.dat").unwrap();
+        let package = Package::mount_from_cursor(&mut file).unwrap();
+        let info = package.entry_info("some/path/foo.txt").unwrap();
+
+        assert_eq!(package.total_uncompressed_size(), 161);
+        assert_eq!(package.total_compressed_size(), info.compressed_length as u64);
+        assert!(package.compression_ratio() < 1.0);
+
+        let mut cursor = package_with(&[("a", &[0; 1000]), ("b", &[1; 500]), ("empty", b"")]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.total_uncompressed_size(), 1500);
+        let compressed: u64 = package.entries_info().iter().map(|info| info.compressed_length as u64).sum();
+        assert_eq!(package.total_compressed_size(), compressed);
+    }
+
+    #[test]
+    fn verifies_all_entries() {
+        let mut cursor = package_with(&[("good.txt", b"good"), ("bad.bin", &[5; 1000]), ("short.txt", b"short")]);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert!(package.verify().is_ok());
+        let bad = package.entry_info("bad.bin").unwrap();
+        let short_at = package.toc_offset() as usize + 2 * ADAT_ENTRY_SIZE as usize + 128 + 4;
+        drop(package);
+
+        {
+            let data = cursor.get_mut();
+            // garble the zlib header of one entry and over-declare the length of another
+            data[bad.offset as usize] ^= 0xff;
+            data[short_at..short_at + 4].copy_from_slice(&6u32.to_le_bytes());
+        }
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let failures = package.verify().unwrap_err();
+        let names: Vec<&str> = failures.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["bad.bin", "short.txt"]);
+        assert!(failures.iter().all(|(_, e)| matches!(e.without_context(), AdatError::Decompress { .. })));
+    }
+
+    #[test]
+    fn finds_by_u0() {
+        let mut cursor = package_with(&[("a.txt", b"a"), ("b.txt", b"b"), ("c.txt", b"c")]);
+        let toc_offset = Package::mount_from_cursor(&mut cursor).unwrap().toc_offset() as usize;
+        for (position, u0) in [(0, 0xdeadbeefu32), (2, 0xdeadbeef)] {
+            let u0_at = toc_offset + position * ADAT_ENTRY_SIZE as usize + 128 + 12;
+            cursor.get_mut()[u0_at..u0_at + 4].copy_from_slice(&u0.to_le_bytes());
+        }
+
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.find_by_u0(0xdeadbeef), vec!["a.txt", "c.txt"]);
+        assert_eq!(package.find_by_u0(0), vec!["b.txt"]);
+        assert!(package.find_by_u0(1).is_empty());
+        assert_eq!(package.entry_info("c.txt").unwrap().u0, 0xdeadbeef);
+    }
+
+    #[test]
+    fn reads_raw_deflate_entries() {
+        let text = "no zlib header here, just deflate. ".repeat(10);
+        let mut cursor = package_with(&[("raw.txt", text.as_bytes()), ("zlib.txt", b"wrapped")]);
+        let toc_offset = Package::mount_from_cursor(&mut cursor).unwrap().toc_offset() as usize;
+        {
+            // point the first entry past its zlib header and before its adler32 trailer
+            let data = cursor.get_mut();
+            let offset_at = toc_offset + 128;
+            let offset = u32le_from_slice(&data[offset_at..offset_at + 4]);
+            let compressed_length = u32le_from_slice(&data[offset_at + 8..offset_at + 12]);
+            data[offset_at..offset_at + 4].copy_from_slice(&(offset + 2).to_le_bytes());
+            data[offset_at + 8..offset_at + 12].copy_from_slice(&(compressed_length - 6).to_le_bytes());
+        }
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.read_text_entry("raw.txt").unwrap(), text);
+        assert_eq!(package.read_text_entry("zlib.txt").unwrap(), "wrapped");
+
+        let mut streamed = String::new();
+        package.open_entry("raw.txt").unwrap().read_to_string(&mut streamed).unwrap();
+        assert_eq!(streamed, text);
+
+        let mut garbage = Vec::new();
+        let error = codec::inflate_into(&[0xff; 16], 100, &mut garbage).unwrap_err();
+        assert!(error.to_string().contains("raw deflate stream"), "{}", error);
+    }
+
+    #[test]
+    fn finds_by_pattern() {
+        let mut cursor = package_with(&[
+            ("some/path/foo.txt", b""),
+            ("some/path/bar.txt", b""),
+            ("some/path/deeper/baz.txt", b""),
+            ("some/other.bin", b"")
+        ]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert_eq!(package.find("some/path/*.txt"), vec!["some/path/bar.txt", "some/path/foo.txt"]);
+        assert_eq!(package.find("**/*.txt").len(), 3);
+        assert!(package.find("*.txt").is_empty());
+        assert_eq!(package.filter(|name| name.ends_with(".bin")), vec!["some/other.bin"]);
+    }
+
+    #[test]
+    fn finds_by_extension() {
+        let mut cursor = package_with(&[
+            ("sprites/hero.PNG", b""),
+            ("sprites/tiles.png", b""),
+            ("sprites/tiles.png.bak", b""),
+            ("with.png/readme", b""),
+            ("icon.png", b"")
+        ]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert_eq!(package.entries_with_extension("png"), vec!["icon.png", "sprites/hero.PNG", "sprites/tiles.png"]);
+        assert_eq!(package.entries_with_extension(".Png"), package.entries_with_extension("png"));
+        assert_eq!(package.entries_with_extension("bak"), vec!["sprites/tiles.png.bak"]);
+        assert!(package.entries_with_extension("jpg").is_empty());
+    }
+
+    #[test]
+    fn sorts_by_size() {
+        let noise: Vec<u8> = (0..400u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut cursor = package_with(&[("zeros.bin", &[0; 5000]), ("noise.bin", &noise), ("small.txt", b"small"), ("also.txt", b"abcde")]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let names = |by: SizeKind| package.entries_sorted_by_size(by).into_iter().map(|info| info.name).collect::<Vec<_>>();
+        assert_eq!(names(SizeKind::Uncompressed), vec!["zeros.bin", "noise.bin", "also.txt", "small.txt"]);
+        assert_eq!(names(SizeKind::Compressed), vec!["noise.bin", "zeros.bin", "also.txt", "small.txt"]);
+    }
+
+    #[test]
+    fn lists_directories() {
+        let mut cursor = package_with(&[
+            ("some/path/foo.txt", b""),
+            ("some/path/deeper/baz.txt", b""),
+            ("some/pathological.txt", b""),
+            ("top.txt", b"")
+        ]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        assert_eq!(package.entries_under("some/path"), vec!["some/path/deeper/baz.txt", "some/path/foo.txt"]);
+        assert_eq!(package.entries_under("some/path/"), package.entries_under("some/path"));
+        assert_eq!(package.entries_under("").len(), 4);
+        assert_eq!(package.collect_names_sorted(), vec!["some/path/deeper/baz.txt", "some/path/foo.txt", "some/pathological.txt", "top.txt"]);
+
+        assert_eq!(package.children("some/path"), vec!["some/path/deeper/", "some/path/foo.txt"]);
+        assert_eq!(package.children("some"), vec!["some/path/", "some/pathological.txt"]);
+        assert_eq!(package.children(""), vec!["some/", "top.txt"]);
+        assert!(package.children("missing").is_empty());
+
+        let counts = package.prefix_counts();
+        assert_eq!(counts.len(), 2);
+        assert_eq!((counts["some"], counts[""]), (3, 1));
+    }
+
+    #[test]
+    fn looks_up_case_insensitively() {
+        let mut cursor = package_with(&[("Some/Path/Foo.txt", b"foo"), ("readme", b"lower"), ("README", b"upper")]);
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert!(matches!(package.read_entry("some/path/foo.TXT"), Err(AdatError::EntryNotFound(_))));
+        drop(package);
+
+        let options = MountOptions::new().case_insensitive(true);
+        let mut package = Package::mount_with_options(&mut cursor, &options).unwrap();
+        assert_eq!(package.read_entry("some/path/foo.TXT").unwrap(), b"foo");
+        assert!(package.contains("SOME/PATH/FOO.TXT"));
+        assert_eq!(package.entry_info("some/path/foo.txt").unwrap().name, "Some/Path/Foo.txt");
+
+        // exact matches still win, only the fallback can be ambiguous
+        assert_eq!(package.read_entry("README").unwrap(), b"upper");
+        match package.read_entry("ReadMe") {
+            Err(AdatError::AmbiguousEntry { candidates, .. }) => assert_eq!(candidates, vec!["README", "readme"]),
+            other => panic!("expected an ambiguous match, got {:?}", other)
+        }
+        assert!(!package.contains("ReadMe"));
+    }
+
+    #[test]
+    fn normalizes_separators() {
+        let mut cursor = package_with(&[("some/path/foo.txt", b"foo"), ("win\\dir\\bar.txt", b"bar")]);
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert!(matches!(package.read_entry("some\\path\\foo.txt"), Err(AdatError::EntryNotFound(_))));
+        assert!(!package.contains("win/dir/bar.txt"));
+        drop(package);
+
+        let options = MountOptions::new().normalize_separators(true);
+        let mut package = Package::mount_with_options(&mut cursor, &options).unwrap();
+        assert_eq!(package.read_entry("some\\path\\foo.txt").unwrap(), b"foo");
+        assert_eq!(package.read_entry("some/path\\foo.txt").unwrap(), b"foo");
+        assert_eq!(package.read_entry("win/dir/bar.txt").unwrap(), b"bar");
+        assert_eq!(package.read_entry("win\\dir\\bar.txt").unwrap(), b"bar");
+        assert_eq!(package.children("win/dir"), vec!["win/dir/bar.txt"]);
+
+        // the stored bytes are still there
+        let info = package.entry_info("win\\dir\\bar.txt").unwrap();
+        assert_eq!((info.name.as_str(), info.name_bytes.as_slice()), ("win/dir/bar.txt", b"win\\dir\\bar.txt".as_slice()));
+        assert_eq!(package.find_by_name_bytes(b"win\\dir\\bar.txt"), Some("win/dir/bar.txt"));
+    }
+
+    #[test]
+    fn looks_up_through_sorted_index() {
+        let names = ["m.txt", "b/z.txt", "a.txt", "b/a.txt", "z.txt"];
+        let mut entries: Vec<(&str, &[u8])> = names.iter().map(|name| (*name, name.as_bytes())).collect();
+        entries.push(("n.txt", b"replaced"));
+        let mut data = package_with(&entries).into_inner();
+        let toc_offset = u32le_from_slice(&data[4..8]) as usize;
+        data[toc_offset + 5 * ADAT_ENTRY_SIZE as usize] = b'm'; // a second m.txt, shadowing the first
+        let mut cursor = Cursor::new(data);
+
+        let hashed = Package::mount_from_cursor(&mut cursor).unwrap();
+        let hashed_sizes = hashed.total_compressed_size();
+        assert_eq!(hashed.duplicate_names(), vec!["m.txt"]);
+        let mut hashed_names = hashed.list_entries();
+        hashed_names.sort_unstable();
+        drop(hashed);
+
+        let options = MountOptions::new().sorted_index(true);
+        let mut package = Package::mount_with_options(&mut cursor, &options).unwrap();
+        assert_eq!(package.list_entries(), hashed_names);
+        assert_eq!(package.list_entries(), vec!["a.txt", "b/a.txt", "b/z.txt", "m.txt", "z.txt"]);
+        assert_eq!(package.total_compressed_size(), hashed_sizes);
+        assert_eq!(package.duplicate_names(), vec!["m.txt"]);
+
+        assert_eq!(package.read_text_entry("m.txt").unwrap(), "replaced");
+        assert_eq!(package.read_text_entry("b/a.txt").unwrap(), "b/a.txt");
+        assert!(matches!(package.read_entry("b"), Err(AdatError::EntryNotFound(_))));
+        assert_eq!(package.children("b"), vec!["b/a.txt", "b/z.txt"]);
+        assert!(package.verify().is_ok());
+        drop(package);
+
+        // duplicates are found without hashing, and rejected like with the hashed index
+        let rejecting = options.reject_duplicates(true);
+        assert!(matches!(Package::mount_with_options(&mut cursor, &rejecting), Err(AdatError::DuplicateEntry(name)) if name == "m.txt"));
+        let (package, rejected) = Package::mount_lenient(&mut cursor, &rejecting).unwrap();
+        assert_eq!(rejected.iter().map(|r| r.position).collect::<Vec<_>>(), vec![5]);
+        assert!(package.duplicate_names().is_empty());
+        assert_eq!(package.list_entries(), hashed_names);
+        assert_eq!(package.entry_info("m.txt").unwrap().length, 5);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_manifest() {
+        let mut file = File::open("TEST.dat").unwrap();
+        let package = Package::mount_from_cursor(&mut file).unwrap();
+
+        let json = serde_json::to_string(&package.manifest()).unwrap();
+        assert!(json.contains(r#""name":"some/path/foo.txt""#));
+        assert!(json.contains(r#""length":161"#));
+
+        let manifest: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(manifest, package.manifest());
+    }
 }
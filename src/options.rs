@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use crate::{Codec, ZlibCodec};
+use crate::{ADAT_MAGIC, ADAT_VERSION};
+
+/// Hard caps for mounting archives from untrusted sources, see `MountOptions::limits`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MountLimits {
+    pub max_entries: Option<u32>, // checked against the header, before the TOC is read
+    pub max_decompressed_size: Option<usize>, // initial read limit, as set by set_max_decompressed_size
+    pub max_name_len: Option<usize> // in bytes, of the stored name
+}
+
+/// Magic and header versions of a format sharing the ADAT layout, see
+/// `Package::mount_with_format`. The default is ADAT itself, version 9.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub magic: [u8; 4],
+    pub versions: Vec<u32> // accepted header versions
+}
+
+impl Default for FormatSpec {
+    fn default() -> Self {
+        FormatSpec {
+            magic: ADAT_MAGIC,
+            versions: vec![ADAT_VERSION]
+        }
+    }
+}
+
+/// What `Package::extract_all_with` does about files that already exist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    Skip, // leave the existing file alone and go on
+    #[default]
+    Overwrite,
+    Error // fail before anything is written
+}
+
+/// Knobs for `Package::extract_all_with`; the defaults are what `extract_all` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractOptions {
+    pub overwrite: OverwritePolicy,
+    pub create_dirs: bool, // without it, every directory written into has to exist already
+    pub flatten: bool // write every file directly into the destination, under its last name component
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            overwrite: OverwritePolicy::Overwrite,
+            create_dirs: true,
+            flatten: false
+        }
+    }
+}
+
+/// Knobs for `Package::mount_with_options` and `OwnedPackage::mount_with_options`.
+///
+/// The defaults match `mount_from_cursor`: only version 9 archives are accepted.
+#[derive(Debug, Clone)]
+pub struct MountOptions {
+    pub(crate) format: FormatSpec,
+    pub(crate) strict_names: bool,
+    pub(crate) reject_unterminated_names: bool,
+    pub(crate) reject_duplicates: bool,
+    pub(crate) reject_toc_overlap: bool,
+    pub(crate) case_insensitive: bool,
+    pub(crate) normalize_separators: bool,
+    pub(crate) allow_empty: bool,
+    pub(crate) sorted_index: bool,
+    pub(crate) limits: MountLimits,
+    pub(crate) codec: Arc<dyn Codec>
+}
+
+impl Default for MountOptions {
+    fn default() -> Self {
+        MountOptions {
+            format: FormatSpec::default(),
+            strict_names: false,
+            reject_unterminated_names: false,
+            reject_duplicates: false,
+            reject_toc_overlap: false,
+            case_insensitive: false,
+            normalize_separators: false,
+            allow_empty: false,
+            sorted_index: false,
+            limits: MountLimits::default(),
+            codec: Arc::new(ZlibCodec)
+        }
+    }
+}
+
+impl MountOptions {
+    pub fn new() -> Self {
+        MountOptions::default()
+    }
+
+    /// Header versions to accept, e.g. `&[8, 9]` for older tooling that
+    /// emits the same layout under version 8.
+    pub fn accept_versions(mut self, versions: &[u32]) -> Self {
+        self.format.versions = versions.to_vec();
+        self
+    }
+
+    /// Magic and versions to accept, for variants of the format that only
+    /// differ from ADAT in those; replaces what `accept_versions` set.
+    pub fn format(mut self, format: FormatSpec) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Fail the mount on entry names that are not valid UTF-8, instead of
+    /// indexing them under their lossily decoded name.
+    pub fn strict_names(mut self, strict: bool) -> Self {
+        self.strict_names = strict;
+        self
+    }
+
+    /// Fail the mount on names that fill all 128 bytes of their field without
+    /// a null terminator, which may have been cut off by the tool that wrote
+    /// them. By default such a name is read as all 128 bytes and
+    /// `Toc::unterminated_names` reports it.
+    pub fn reject_unterminated_names(mut self, reject: bool) -> Self {
+        self.reject_unterminated_names = reject;
+        self
+    }
+
+    /// Fail the mount if two TOC entries share a name. By default the last
+    /// one wins lookups and `Package::duplicate_names` reports the collision.
+    pub fn reject_duplicates(mut self, reject: bool) -> Self {
+        self.reject_duplicates = reject;
+        self
+    }
+
+    /// Fail the mount if the data of an entry overlaps the TOC, a sign of a
+    /// corrupt or tampered archive: reading such an entry returns TOC bytes.
+    pub fn reject_toc_overlap(mut self, reject: bool) -> Self {
+        self.reject_toc_overlap = reject;
+        self
+    }
+
+    /// Let lookups that match no stored name exactly fall back to comparing
+    /// names case-insensitively. A name that matches several stored names
+    /// that way fails with `AdatError::AmbiguousEntry`.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Index entries under their names with every `\` turned into `/`, and
+    /// do the same to the names looked up, so `some\path\foo.txt` finds
+    /// `some/path/foo.txt` and the other way around. The stored bytes stay
+    /// available through `EntryInfo::name_bytes` and `find_by_name_bytes`.
+    pub fn normalize_separators(mut self, normalize: bool) -> Self {
+        self.normalize_separators = normalize;
+        self
+    }
+
+    /// Mount archives whose TOC has no entries at all, which fail with
+    /// `AdatError::EmptyToc` by default.
+    pub fn allow_empty(mut self, allow: bool) -> Self {
+        self.allow_empty = allow;
+        self
+    }
+
+    /// Index names with a `Vec` of positions sorted by name and look them up
+    /// by binary search, instead of with a `HashMap` holding a copy of every
+    /// name. Takes much less memory for archives with very many entries, at
+    /// the cost of slower lookups; names are then also listed in sorted order.
+    pub fn sorted_index(mut self, sorted: bool) -> Self {
+        self.sorted_index = sorted;
+        self
+    }
+
+    /// Caps on the entry count, entry sizes and name lengths. Exceeding
+    /// `max_entries` or `max_name_len` fails the mount.
+    pub fn limits(mut self, limits: MountLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Codec to decompress entries with, `ZlibCodec` by default.
+    pub fn codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+}
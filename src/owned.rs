@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::{Seek, Read};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use crate::{AdatError, Result, MountOptions, Package, PackageCursor};
+
+/// A `Package` that owns its reader instead of borrowing it.
+///
+/// Dereferences to `Package`, so the whole reading API is available on it,
+/// and it can be returned from the function that opened the file.
+/// It is `Send` and `Sync` under the same conditions on `T` as `Package`,
+/// so an `OwnedPackage<File>` can be moved into another thread.
+#[derive(Debug)]
+pub struct OwnedPackage<T: Read + Seek + 'static> {
+    package: Package<'static, T>
+}
+
+impl<T: Read + Seek + 'static> OwnedPackage<T> {
+    pub fn mount(cursor: T) -> Result<Self> {
+        OwnedPackage::mount_with_options(cursor, &MountOptions::default())
+    }
+
+    pub fn mount_with_options(cursor: T, options: &MountOptions) -> Result<Self> {
+        Package::mount_package_cursor(PackageCursor::Owned(cursor), options).map(|package| {
+            OwnedPackage { package }
+        })
+    }
+
+    pub fn into_inner(self) -> T {
+        match self.package.cursor {
+            PackageCursor::Owned(cursor) => cursor,
+            PackageCursor::Borrowed(_) => unreachable!("OwnedPackage always owns its cursor")
+        }
+    }
+}
+
+/// `Read + Seek` as a single trait, for readers whose type is only known
+/// at runtime: a `Box<dyn ReadSeek>` is a reader like any other.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+impl OwnedPackage<Box<dyn ReadSeek>> {
+    /// Mounts a boxed reader of any type, e.g. a file, a cursor or a
+    /// range-request adapter picked at runtime.
+    pub fn mount_dyn(reader: Box<dyn ReadSeek>) -> Result<Self> {
+        OwnedPackage::mount(reader)
+    }
+}
+
+impl OwnedPackage<File> {
+    pub fn mount_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        OwnedPackage::mount(File::open(path)?)
+    }
+}
+
+impl TryFrom<File> for OwnedPackage<File> {
+    type Error = AdatError;
+
+    fn try_from(file: File) -> Result<Self> {
+        OwnedPackage::mount(file)
+    }
+}
+
+impl TryFrom<PathBuf> for OwnedPackage<File> {
+    type Error = AdatError;
+
+    fn try_from(path: PathBuf) -> Result<Self> {
+        OwnedPackage::mount_from_path(path)
+    }
+}
+
+impl<T: Read + Seek + 'static> Deref for OwnedPackage<T> {
+    type Target = Package<'static, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.package
+    }
+}
+
+impl<T: Read + Seek + 'static> DerefMut for OwnedPackage<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.package
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    fn mount_test_dat() -> Result<OwnedPackage<File>> {
+        OwnedPackage::mount_from_path("TEST.dat")
+    }
+
+    #[test]
+    fn outlives_opening_function() {
+        let mut package = mount_test_dat().unwrap();
+
+        assert_eq!(package.list_entries(), vec!["some/path/foo.txt"]);
+        assert!(package.read_text_entry("some/path/foo.txt").unwrap().contains("hello world"));
+
+        let mut file = package.into_inner();
+        let mut magic: [u8; 4] = [0; 4];
+        file.seek(std::io::SeekFrom::Start(0)).unwrap();
+        file.read_exact(&mut magic).unwrap();
+        assert_eq!(&magic, b"ADAT");
+
+        assert!(OwnedPackage::mount_from_path(String::from("TEST.dat")).is_ok());
+        assert!(OwnedPackage::mount_from_path(std::path::PathBuf::from("TEST.dat")).is_ok());
+    }
+
+    #[test]
+    fn converts_from_files_and_paths() {
+        let mut file = File::open("TEST.dat").unwrap();
+        let package = Package::try_from(&mut file).unwrap();
+        assert_eq!(package.len(), 1);
+
+        let package: OwnedPackage<File> = File::open("TEST.dat").unwrap().try_into().unwrap();
+        assert!(package.contains("some/path/foo.txt"));
+        let package = OwnedPackage::try_from(PathBuf::from("TEST.dat")).unwrap();
+        assert_eq!(package.len(), 1);
+
+        assert!(matches!(OwnedPackage::try_from(PathBuf::from("missing.dat")), Err(AdatError::Io(_))));
+    }
+
+    #[test]
+    fn mounts_readers_picked_at_runtime() {
+        let bytes = std::fs::read("TEST.dat").unwrap();
+        let readers: Vec<Box<dyn ReadSeek>> = vec![Box::new(File::open("TEST.dat").unwrap()), Box::new(std::io::Cursor::new(bytes))];
+
+        for reader in readers {
+            let mut package = OwnedPackage::mount_dyn(reader).unwrap();
+            assert!(package.read_text_entry("some/path/foo.txt").unwrap().contains("hello world"));
+        }
+
+        // borrowed as well
+        let mut reader: Box<dyn ReadSeek> = Box::new(File::open("TEST.dat").unwrap());
+        let package = Package::mount_from_cursor(&mut reader).unwrap();
+        assert_eq!(package.len(), 1);
+    }
+
+    #[test]
+    fn moves_across_threads() {
+        assert_send_sync::<OwnedPackage<File>>();
+        assert_send_sync::<Package<'static, File>>();
+        assert_send_sync::<crate::Toc>();
+
+        let mut package = mount_test_dat().unwrap();
+        let text = thread::spawn(move || package.read_text_entry("some/path/foo.txt").unwrap()).join().unwrap();
+        assert!(text.contains("hello world"));
+
+        let package = mount_test_dat().unwrap();
+        let names = thread::scope(|scope| {
+            scope.spawn(|| package.list_entries()).join().unwrap()
+        });
+        assert_eq!(names, package.list_entries());
+    }
+}
@@ -0,0 +1,238 @@
+use std::io::{self, Seek, SeekFrom, Read};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{Result, MountOptions, PackageEntry, Toc};
+use crate::{text_from_bytes, text_from_bytes_lossy};
+
+/// A mounted archive that keeps its reader behind a `Mutex`, so entries
+/// can be read through a shared reference.
+///
+/// Every read locks the reader for its seek and read of the compressed
+/// data; decompression happens after the lock is released. A `&SharedPackage`
+/// can be handed to any number of consumers, and to other threads when `T`
+/// is `Send`. Dereferences to the archive's `Toc`, like `Package`.
+#[derive(Debug)]
+pub struct SharedPackage<T: Read + Seek> {
+    cursor: Mutex<T>,
+    toc: Toc,
+    max_decompressed_size: Option<usize>
+}
+
+impl<T: Read + Seek> SharedPackage<T> {
+    pub fn mount(cursor: T) -> Result<Self> {
+        SharedPackage::mount_with_options(cursor, &MountOptions::default())
+    }
+
+    pub fn mount_with_options(mut cursor: T, options: &MountOptions) -> Result<Self> {
+        let toc = Toc::read_toc(&mut cursor, options)?;
+
+        Ok(SharedPackage {
+            cursor: Mutex::new(cursor),
+            toc,
+            max_decompressed_size: options.limits.max_decompressed_size
+        })
+    }
+
+    /// Like `Package::set_max_decompressed_size`.
+    pub fn set_max_decompressed_size(&mut self, limit: usize) {
+        self.max_decompressed_size = Some(limit);
+    }
+
+    pub fn max_decompressed_size(&self) -> Option<usize> {
+        self.max_decompressed_size
+    }
+
+    // every read seeks first, so a reader left behind by a panicking one is still usable
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.cursor.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn decompress_into(&self, pe: &PackageEntry, out: &mut Vec<u8>) -> Result<usize> {
+        let compressed_data = pe.read_entry_raw(&mut *self.lock())?;
+        pe.decode(&*self.toc.codec, &compressed_data, out)
+    }
+
+    /// Reads the entry at the given TOC position; this also reaches entries
+    /// shadowed by a later duplicate of their name.
+    pub fn read_entry_by_index(&self, index: usize) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::new();
+        let pe = self.toc.readable_entry_at(index, self.max_decompressed_size)?;
+        self.decompress_into(pe, &mut data)?;
+        Ok(data)
+    }
+
+    pub fn read_entry(&self, entry_path: &str) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::new();
+        self.read_entry_into(entry_path, &mut data)?;
+        Ok(data)
+    }
+
+    /// Like `read_entry`, but decompresses into `out`, reusing its allocation.
+    ///
+    /// `out` is cleared first; returns the number of bytes written to it.
+    pub fn read_entry_into(&self, entry_path: &str, out: &mut Vec<u8>) -> Result<usize> {
+        let position = self.toc.position(entry_path)?;
+        let pe = self.toc.readable_entry_at(position, self.max_decompressed_size)?;
+        self.decompress_into(pe, out)
+    }
+
+    /// Reads the compressed bytes of an entry exactly as stored, without inflating them.
+    pub fn read_entry_raw(&self, entry_path: &str) -> Result<Vec<u8>> {
+        let pe = self.toc.lookup(entry_path)?;
+        pe.read_entry_raw(&mut *self.lock())
+    }
+
+    pub fn read_text_entry(&self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).and_then(text_from_bytes)
+    }
+
+    /// Like `read_text_entry`, but replaces invalid UTF-8 sequences with U+FFFD.
+    pub fn read_text_entry_lossy(&self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).map(text_from_bytes_lossy)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.cursor.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A reader over a handle shared behind an `Arc<Mutex<T>>`, for mounting
+/// any number of packages on one open file.
+///
+/// Every reader keeps a position of its own and locks the handle for each
+/// `read`, seeking it there first, so readers never see each other's
+/// offsets. Under contention they take turns: a read waits for the one in
+/// progress, and all of them together go no faster than the single handle.
+/// `Package` reads the compressed data of an entry before decompressing it,
+/// so the handle is not held while inflating. Cloning gives a reader at the
+/// same position sharing the same handle.
+#[derive(Debug)]
+pub struct SharedReader<T: Read + Seek> {
+    inner: Arc<Mutex<T>>,
+    position: u64
+}
+
+impl<T: Read + Seek> SharedReader<T> {
+    /// A reader at the start of the shared handle.
+    pub fn new(inner: Arc<Mutex<T>>) -> Self {
+        SharedReader { inner, position: 0 }
+    }
+
+    pub fn get_ref(&self) -> &Arc<Mutex<T>> {
+        &self.inner
+    }
+
+    // like SharedPackage::lock, every read seeks first
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Read + Seek> Clone for SharedReader<T> {
+    fn clone(&self) -> Self {
+        SharedReader { inner: self.inner.clone(), position: self.position }
+    }
+}
+
+impl<T: Read + Seek> Read for SharedReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = {
+            let mut inner = self.lock();
+            inner.seek(SeekFrom::Start(self.position))?;
+            inner.read(buf)?
+        };
+
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<T: Read + Seek> Seek for SharedReader<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => Some(self.lock().seek(SeekFrom::End(delta))?),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta)
+        };
+
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+        Ok(self.position)
+    }
+}
+
+impl<T: Read + Seek> Deref for SharedPackage<T> {
+    type Target = Toc;
+
+    fn deref(&self) -> &Toc {
+        &self.toc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AdatError, OwnedPackage, PackageWriter};
+    use std::io::Cursor;
+    use std::thread;
+
+    #[test]
+    fn reads_through_shared_reference() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a.txt", b"shared").unwrap();
+        writer.add_entry("b.bin", &[4; 30_000]).unwrap();
+        let package = SharedPackage::mount(writer.finalize().unwrap()).unwrap();
+
+        // two reads while a borrow of the package is alive
+        let names = package.entries_in_order();
+        let (a, b) = (package.read_text_entry("a.txt").unwrap(), package.read_entry("b.bin").unwrap());
+        assert_eq!(names.collect::<Vec<_>>(), vec!["a.txt", "b.bin"]);
+        assert_eq!(a, "shared");
+        assert_eq!(b, vec![4; 30_000]);
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(package.read_entry_by_index(1).unwrap().len(), 30_000);
+                    assert_eq!(package.read_text_entry("a.txt").unwrap(), "shared");
+                });
+            }
+        });
+
+        assert!(matches!(package.read_entry("c"), Err(AdatError::EntryNotFound(_))));
+        assert!(package.into_inner().get_ref().starts_with(b"ADAT"));
+    }
+
+    #[test]
+    fn mounts_many_on_one_handle() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        for i in 0..4u8 {
+            writer.add_entry(&format!("entry{}.bin", i), &vec![i; 40_000]).unwrap();
+        }
+        let handle = Arc::new(Mutex::new(writer.finalize().unwrap()));
+
+        thread::scope(|scope| {
+            for i in 0..4u8 {
+                let reader = SharedReader::new(handle.clone());
+                scope.spawn(move || {
+                    let mut package = OwnedPackage::mount(reader).unwrap();
+                    for _ in 0..10 {
+                        assert_eq!(package.read_entry(&format!("entry{}.bin", i)).unwrap(), vec![i; 40_000]);
+                    }
+                });
+            }
+        });
+
+        let mut reader = SharedReader::new(handle.clone());
+        reader.seek(SeekFrom::Start(4)).unwrap();
+        let mut other = reader.clone();
+        assert_eq!(other.seek(SeekFrom::Current(-4)).unwrap(), 0);
+        let mut magic = [0; 4];
+        other.read_exact(&mut magic).unwrap();
+        assert_eq!(&magic, b"ADAT");
+        assert_eq!(reader.stream_position().unwrap(), 4);
+        assert!(reader.seek(SeekFrom::Current(-5)).is_err());
+    }
+}
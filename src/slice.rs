@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::ops::Deref;
+
+#[cfg(feature = "mmap")]
+use std::fs::File;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+use crate::{Result, MountOptions, PackageEntry, Toc};
+use crate::{text_from_bytes, text_from_bytes_lossy};
+
+/// A mounted archive whose whole contents are already in memory.
+///
+/// Compressed entry data is sliced straight out of the backing buffer and
+/// fed to the decompressor, so reads need neither a cursor nor `&mut self`.
+/// When `B` is `Sync`, one package can be read from many threads at once.
+/// Dereferences to the archive's `Toc`, like `Package`.
+#[derive(Debug)]
+pub struct SlicePackage<B: AsRef<[u8]>> {
+    data: B,
+    toc: Toc,
+    max_decompressed_size: Option<usize>
+}
+
+/// A `SlicePackage` over a memory-mapped archive file.
+#[cfg(feature = "mmap")]
+pub type MmapPackage = SlicePackage<Mmap>;
+
+#[cfg(feature = "mmap")]
+impl SlicePackage<Mmap> {
+    /// Maps the archive at `path` into memory and mounts it.
+    ///
+    /// The file must not be truncated or rewritten while it is mounted.
+    pub fn mount_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        SlicePackage::mount_mmap_with_options(path, &MountOptions::default())
+    }
+
+    pub fn mount_mmap_with_options(path: impl AsRef<Path>, options: &MountOptions) -> Result<Self> {
+        let file = File::open(path)?;
+        // sound as long as nobody changes the file under us, see above
+        let map = unsafe { Mmap::map(&file)? };
+        SlicePackage::mount_slice(map, options)
+    }
+}
+
+impl<B: AsRef<[u8]>> SlicePackage<B> {
+    /// Mounts an archive held in memory, e.g. a `Vec<u8>` or a `&[u8]`.
+    pub fn mount_from_bytes(data: B) -> Result<Self> {
+        SlicePackage::mount_slice(data, &MountOptions::default())
+    }
+
+    pub fn mount_from_bytes_with_options(data: B, options: &MountOptions) -> Result<Self> {
+        SlicePackage::mount_slice(data, options)
+    }
+
+    fn mount_slice(data: B, options: &MountOptions) -> Result<Self> {
+        let toc = Toc::read_toc(&mut Cursor::new(data.as_ref()), options)?;
+
+        Ok(SlicePackage {
+            data,
+            toc,
+            max_decompressed_size: options.limits.max_decompressed_size
+        })
+    }
+
+    /// Like `Package::set_max_decompressed_size`.
+    pub fn set_max_decompressed_size(&mut self, limit: usize) {
+        self.max_decompressed_size = Some(limit);
+    }
+
+    pub fn max_decompressed_size(&self) -> Option<usize> {
+        self.max_decompressed_size
+    }
+
+    // entry bounds were checked against the data length on mount
+    fn compressed_data(&self, pe: &PackageEntry) -> &[u8] {
+        let offset = pe.offset as usize;
+        &self.data.as_ref()[offset..offset + pe.compressed_length]
+    }
+
+    fn decompress_into(&self, pe: &PackageEntry, out: &mut Vec<u8>) -> Result<usize> {
+        pe.decode(&*self.toc.codec, self.compressed_data(pe), out)
+    }
+
+    /// Reads the entry at the given TOC position; this also reaches entries
+    /// shadowed by a later duplicate of their name.
+    pub fn read_entry_by_index(&self, index: usize) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::new();
+        let pe = self.toc.readable_entry_at(index, self.max_decompressed_size)?;
+        self.decompress_into(pe, &mut data)?;
+        Ok(data)
+    }
+
+    pub fn read_entry(&self, entry_path: &str) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::new();
+        self.read_entry_into(entry_path, &mut data)?;
+        Ok(data)
+    }
+
+    /// Like `read_entry`, but decompresses into `out`, reusing its allocation.
+    ///
+    /// `out` is cleared first; returns the number of bytes written to it.
+    pub fn read_entry_into(&self, entry_path: &str, out: &mut Vec<u8>) -> Result<usize> {
+        let pe = self.toc.readable_entry(entry_path, self.max_decompressed_size)?;
+        self.decompress_into(pe, out)
+    }
+
+    /// The compressed bytes of an entry exactly as stored, borrowed from the backing data.
+    pub fn read_entry_raw(&self, entry_path: &str) -> Result<&[u8]> {
+        let pe = self.toc.lookup(entry_path)?;
+        Ok(self.compressed_data(pe))
+    }
+
+    pub fn read_text_entry(&self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).and_then(text_from_bytes)
+    }
+
+    /// Like `read_text_entry`, but replaces invalid UTF-8 sequences with U+FFFD.
+    pub fn read_text_entry_lossy(&self, entry_path: &str) -> Result<String> {
+        self.read_entry(entry_path).map(text_from_bytes_lossy)
+    }
+
+    /// Like `read_text_entry_lossy`, but borrows the text of a stored entry
+    /// straight from the backing data when it is valid UTF-8.
+    ///
+    /// A compressed entry is decompressed into a new buffer, which becomes
+    /// the `String` without a copy; only invalid UTF-8 is copied, to replace it.
+    pub fn read_text_entry_cow(&self, entry_path: &str) -> Result<Cow<'_, str>> {
+        let pe = self.toc.readable_entry(entry_path, self.max_decompressed_size)?;
+        if pe.is_stored() {
+            return Ok(String::from_utf8_lossy(self.compressed_data(pe)));
+        }
+
+        let mut data: Vec<u8> = Vec::new();
+        self.decompress_into(pe, &mut data)?;
+        Ok(Cow::Owned(text_from_bytes_lossy(data)))
+    }
+
+    pub fn into_inner(self) -> B {
+        self.data
+    }
+}
+
+impl<B: AsRef<[u8]>> Deref for SlicePackage<B> {
+    type Target = Toc;
+
+    fn deref(&self) -> &Toc {
+        &self.toc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AdatError, PackageWriter};
+
+    fn package_bytes() -> Vec<u8> {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("a.txt", b"in memory").unwrap();
+        writer.add_entry("b.bin", &[3; 10_000]).unwrap();
+        writer.finalize().unwrap().into_inner()
+    }
+
+    #[test]
+    fn mounts_from_bytes() {
+        let bytes = package_bytes();
+
+        let package = SlicePackage::mount_from_bytes(&bytes[..]).unwrap();
+        assert_eq!(package.read_text_entry("a.txt").unwrap(), "in memory");
+        assert_eq!(package.read_entry("b.bin").unwrap(), vec![3; 10_000]);
+
+        let raw = package.read_entry_raw("b.bin").unwrap();
+        let offset = package.entry_info("b.bin").unwrap().offset as usize;
+        assert_eq!(raw.as_ptr(), bytes[offset..].as_ptr());
+
+        let archive_len = package.toc_offset() as usize + package.toc_length() as usize;
+        drop(package);
+
+        let mut owned = SlicePackage::mount_from_bytes(bytes).unwrap();
+        owned.set_max_decompressed_size(100);
+        assert!(matches!(owned.read_entry("b.bin"), Err(AdatError::EntryTooLarge { .. })));
+        assert_eq!(owned.read_entry_by_index(0).unwrap(), b"in memory");
+        assert_eq!(owned.into_inner().len(), archive_len);
+
+        assert!(matches!(SlicePackage::mount_from_bytes(b"ADAT"), Err(AdatError::Io(_))));
+    }
+
+    #[test]
+    fn borrows_stored_text() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("short.txt", b"as is").unwrap();
+        writer.add_entry("long.txt", "compressed ".repeat(100).as_bytes()).unwrap();
+        writer.add_entry("bad.txt", b"\xff\xfe").unwrap();
+        let bytes = writer.finalize().unwrap().into_inner();
+        let package = SlicePackage::mount_from_bytes(&bytes[..]).unwrap();
+
+        assert!(matches!(package.read_text_entry_cow("short.txt").unwrap(), Cow::Borrowed("as is")));
+        let long = package.read_text_entry_cow("long.txt").unwrap();
+        assert!(matches!(long, Cow::Owned(_)));
+        assert_eq!(long, "compressed ".repeat(100));
+        assert_eq!(package.read_text_entry_cow("bad.txt").unwrap(), "\u{FFFD}\u{FFFD}");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn reads_through_mmap() {
+        let bytes = package_bytes();
+
+        let path = std::env::temp_dir().join(format!("adat-format-mmap-{}.dat", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let package = MmapPackage::mount_mmap(&path).unwrap();
+        assert_eq!(package.len(), 2);
+        assert_eq!(package.read_text_entry("a.txt").unwrap(), "in memory");
+        assert_eq!(package.read_entry("b.bin").unwrap(), vec![3; 10_000]);
+        assert!(matches!(package.read_entry("c"), Err(AdatError::EntryNotFound(_))));
+
+        let mut cursor = Cursor::new(bytes);
+        let mut cursor_package = crate::Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.read_entry_raw("b.bin").unwrap(), cursor_package.read_entry_raw("b.bin").unwrap());
+
+        drop(package);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,375 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Take};
+use std::io::{Error, ErrorKind};
+
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+
+use crate::{AdatError, DecompressErrorKind};
+use crate::codec::{has_zlib_header, stream_kind};
+
+pub(crate) const STREAM_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Streams the decompressed contents of a single entry.
+///
+/// Compressed data is pulled from the package cursor in small chunks, so an
+/// entry can be copied somewhere without holding all of it in memory. Ending
+/// up with anything other than the declared length is an error. Entries of
+/// codecs other than deflate are decompressed up front instead.
+pub struct EntryReader<'a, T: Read> {
+    input: Take<&'a mut T>,
+    state: Box<InflateState>,
+    buffer: Box<[u8]>, // compressed bytes read from input but not yet inflated
+    pos: usize,
+    filled: usize,
+    length: u64, // declared decompressed length
+    produced: u64,
+    zlib: bool, // decided from the first compressed bytes, see has_zlib_header
+    started: bool,
+    finished: bool,
+    stored: bool, // input is the entry data itself
+    decoded: Option<Cursor<Vec<u8>>> // already decompressed by a non-deflate codec
+}
+
+impl<'a, T: Read> EntryReader<'a, T> {
+    // cursor has to be positioned at the start of the compressed data already;
+    // compressed data is read buffer_size bytes at a time
+    pub(crate) fn new(cursor: &'a mut T, compressed_length: u64, length: u64, buffer_size: usize) -> Self {
+        EntryReader {
+            input: cursor.take(compressed_length),
+            state: InflateState::new_boxed(DataFormat::Zlib),
+            buffer: vec![0; buffer_size.max(2)].into_boxed_slice(), // room for the header sniffed below
+            pos: 0,
+            filled: 0,
+            length,
+            produced: 0,
+            zlib: true,
+            started: false,
+            finished: false,
+            stored: false,
+            decoded: None
+        }
+    }
+
+    // cursor has to be positioned at the start of the entry data already
+    pub(crate) fn stored(cursor: &'a mut T, length: u64) -> Self {
+        let mut reader = EntryReader::new(cursor, length, length, 0); // read straight into the output, not buffered
+        reader.stored = true;
+        reader
+    }
+
+    pub(crate) fn decoded(cursor: &'a mut T, data: Vec<u8>) -> Self {
+        EntryReader {
+            input: cursor.take(0),
+            state: InflateState::new_boxed(DataFormat::Zlib),
+            buffer: Box::new([]),
+            pos: 0,
+            filled: 0,
+            length: data.len() as u64,
+            produced: 0,
+            zlib: false,
+            started: true,
+            finished: true,
+            stored: false,
+            decoded: Some(Cursor::new(data))
+        }
+    }
+
+    /// Declared decompressed length of the entry.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    // hands back the package cursor, wherever the stream left it
+    fn into_cursor(self) -> &'a mut T {
+        self.input.into_inner()
+    }
+}
+
+impl<T: Read> Read for EntryReader<'_, T> {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if let Some(decoded) = &mut self.decoded {
+            return decoded.read(out);
+        }
+        if out.is_empty() || self.finished {
+            return Ok(0);
+        }
+        if self.stored {
+            let read = self.input.read(out)?;
+            self.produced += read as u64;
+            if read == 0 && self.produced < self.length {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "stored entry data ended early"));
+            }
+            return Ok(read);
+        }
+
+        loop {
+            if self.pos == self.filled {
+                self.filled = self.input.read(&mut self.buffer)?;
+                self.pos = 0;
+            }
+
+            if !self.started {
+                // readers may hand out less than asked for, the zlib header takes two bytes
+                while self.filled < 2 {
+                    let read = self.input.read(&mut self.buffer[self.filled..])?;
+                    if read == 0 {
+                        break;
+                    }
+                    self.filled += read;
+                }
+                self.started = true;
+                self.zlib = has_zlib_header(&self.buffer[..self.filled]);
+                if !self.zlib {
+                    self.state.reset(DataFormat::Raw);
+                }
+            }
+
+            let result = inflate(&mut self.state, &self.buffer[self.pos..self.filled], out, MZFlush::None);
+            self.pos += result.bytes_consumed;
+            self.produced += result.bytes_written as u64;
+
+            if self.produced > self.length {
+                return Err(AdatError::Decompress {
+                    kind: DecompressErrorKind::OutputLimit,
+                    message: format!("entry decompresses to more than its declared {} bytes", self.length)
+                }.into());
+            }
+
+            match result.status {
+                Ok(MZStatus::StreamEnd) => {
+                    self.finished = true;
+                    if self.produced != self.length {
+                        return Err(AdatError::LengthMismatch {
+                            expected: self.length as usize,
+                            got: self.produced as usize
+                        }.into());
+                    }
+                    return Ok(result.bytes_written);
+                }
+                Ok(_) if result.bytes_written > 0 => return Ok(result.bytes_written),
+                Ok(_) => continue,
+                Err(MZError::Buf) => {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, "compressed entry data ended early"));
+                }
+                Err(e) => {
+                    let message = format!("{:?} ({} stream)", e, stream_kind(self.zlib));
+                    return Err(AdatError::Decompress { kind: DecompressErrorKind::InvalidData, message }.into());
+                }
+            }
+        }
+    }
+}
+
+/// An `EntryReader` that can also seek within the decompressed contents.
+///
+/// Seeking only records the position; the work happens on the next read.
+/// Stored entries, and those of codecs other than deflate, which are held
+/// in memory, seek in constant time. Deflate streams cannot be entered in
+/// the middle: a forward seek decompresses and drops everything up to the
+/// new position, and a backward seek starts over from the beginning of the
+/// entry, so it costs as much as reading up to the target again. Backtracking
+/// often over a compressed entry is better served by `read_entry` and a
+/// `Cursor` around the result, at the cost of holding all of it in memory.
+pub struct SeekableEntryReader<'a, T: Read + Seek> {
+    reader: Option<EntryReader<'a, T>>, // only ever empty while restarting
+    offset: u64, // of the compressed data
+    compressed_length: u64,
+    length: u64, // declared decompressed length, restarted stored readers only cover the rest
+    position: u64, // of reader in the decompressed output, UNKNOWN after an error
+    target: u64 // where the next read starts
+}
+
+// a failed read leaves the stream anywhere, so the next read starts over
+const UNKNOWN: u64 = u64::MAX;
+
+impl<'a, T: Read + Seek> SeekableEntryReader<'a, T> {
+    // reader has to be fresh, as opened over the entry at offset
+    pub(crate) fn new(reader: EntryReader<'a, T>, offset: u64, compressed_length: u64) -> Self {
+        let length = reader.length;
+        SeekableEntryReader { reader: Some(reader), offset, compressed_length, length, position: 0, target: 0 }
+    }
+
+    /// Declared decompressed length of the entry.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    fn reader(&self) -> &EntryReader<'a, T> {
+        self.reader.as_ref().expect("reader is put back by restart")
+    }
+
+    fn reader_mut(&mut self) -> &mut EntryReader<'a, T> {
+        self.reader.as_mut().expect("reader is put back by restart")
+    }
+
+    // reopens the entry at start, which has to be 0 unless it is stored
+    fn restart(&mut self, start: u64) -> io::Result<()> {
+        let (stored, length) = (self.reader().stored, self.length);
+        let cursor = self.reader.take().map(EntryReader::into_cursor).expect("reader is put back by restart");
+
+        let sought = cursor.seek(SeekFrom::Start(self.offset + start));
+        self.reader = Some(if stored {
+            EntryReader::stored(cursor, length - start)
+        } else {
+            EntryReader::new(cursor, self.compressed_length, length, STREAM_BUFFER_SIZE)
+        });
+        self.position = if sought.is_ok() { start } else { UNKNOWN };
+        sought.map(|_| ())
+    }
+
+    // moves the stream to target, decompressing and dropping what lies before it
+    fn catch_up(&mut self) -> io::Result<()> {
+        let stored = self.reader().stored;
+        if self.position == self.target {
+            return Ok(());
+        }
+        if stored {
+            return self.restart(self.target);
+        }
+        if self.target < self.position {
+            self.restart(0)?;
+        }
+
+        let skip = self.target - self.position;
+        self.position = UNKNOWN;
+        let skipped = io::copy(&mut self.reader_mut().take(skip), &mut io::sink())?;
+        if skipped < skip {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "entry stream ended before the seek position"));
+        }
+        self.position = self.target;
+        Ok(())
+    }
+}
+
+impl<T: Read + Seek> Read for SeekableEntryReader<'_, T> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.target > self.len() {
+            return Ok(0);
+        }
+        let target = self.target;
+        if let Some(decoded) = &mut self.reader_mut().decoded {
+            decoded.set_position(target);
+            let read = decoded.read(out)?;
+            self.target += read as u64;
+            return Ok(read);
+        }
+
+        self.catch_up()?;
+        let read = self.reader_mut().read(out).inspect_err(|_| self.position = UNKNOWN)?;
+        self.position += read as u64;
+        self.target = self.position;
+        Ok(read)
+    }
+}
+
+impl<T: Read + Seek> Seek for SeekableEntryReader<'_, T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.target.checked_add_signed(delta)
+        };
+
+        self.target = target.ok_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+        Ok(self.target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Package, PackageWriter};
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    fn test_package() -> Cursor<Vec<u8>> {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("data.bin", &data).unwrap();
+        writer.add_entry("empty", b"").unwrap();
+        writer.finalize().unwrap()
+    }
+
+    #[test]
+    fn streams_entry() {
+        let mut cursor = test_package();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let expected = package.read_entry("data.bin").unwrap();
+
+        let mut reader = package.open_entry("data.bin").unwrap();
+        assert_eq!(reader.len(), 200_000);
+        let mut out = Vec::new();
+        assert_eq!(std::io::copy(&mut reader, &mut out).unwrap(), 200_000);
+        assert_eq!(out, expected);
+
+        let mut out = Vec::new();
+        package.open_entry("empty").unwrap().read_to_end(&mut out).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn errors_on_early_end() {
+        let mut cursor = test_package();
+        {
+            // declare one more decompressed byte than is actually there
+            let data = cursor.get_mut();
+            let toc_offset = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+            let length_at = toc_offset + 128 + 4;
+            data[length_at..length_at + 4].copy_from_slice(&200_001u32.to_le_bytes());
+        }
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let error = std::io::copy(&mut package.open_entry("data.bin").unwrap(), &mut std::io::sink()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+        let error = crate::AdatError::from_stream(error);
+        assert!(matches!(error, crate::AdatError::LengthMismatch { expected: 200_001, got: 200_000 }));
+    }
+
+    #[test]
+    fn seeks_within_entries() {
+        let mut cursor = test_package();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let expected = package.read_entry("data.bin").unwrap();
+
+        let mut reader = package.open_entry_seekable("data.bin").unwrap();
+        assert_eq!(reader.len(), 200_000);
+        let mut chunk = [0; 100];
+        for start in [150_000u64, 10, 10, 199_950, 0] {
+            assert_eq!(reader.seek(SeekFrom::Start(start)).unwrap(), start);
+            let read = reader.read(&mut chunk).unwrap();
+            let start = start as usize;
+            assert_eq!(chunk[..read], expected[start..start + read]);
+        }
+
+        assert_eq!(reader.seek(SeekFrom::End(-5)).unwrap(), 199_995);
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, expected[199_995..]);
+        assert_eq!(reader.seek(SeekFrom::Current(-200_000)).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-1)).is_err());
+        reader.seek(SeekFrom::Start(300_000)).unwrap();
+        assert_eq!(reader.read(&mut chunk).unwrap(), 0);
+        drop(reader);
+
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("stored.txt", b"stored").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let mut reader = package.open_entry_seekable("stored.txt").unwrap();
+        let mut text = String::new();
+        reader.seek(SeekFrom::Start(3)).unwrap();
+        reader.read_to_string(&mut text).unwrap();
+        reader.rewind().unwrap();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "redstored");
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{self, Cursor, Seek, Read, Write, SeekFrom};
+use std::ops::{Deref, DerefMut};
+
+use crate::{Result, PackageWriter};
+
+/// Writes an archive to an output that cannot seek, such as a pipe or a socket.
+///
+/// Entries go through a regular `PackageWriter` over a seekable buffer;
+/// `finalize` then copies the finished archive to the output front to back.
+/// The buffer holds the whole compressed archive, so by default, with a
+/// `Vec`, memory use grows with the archive, where writing to a seekable
+/// output directly only ever holds one entry. Pass a temporary file to
+/// `with_buffer` to keep memory flat at the cost of writing everything twice.
+///
+/// Dereferences to the inner `PackageWriter` for adding entries.
+#[derive(Debug)]
+pub struct PackageStreamWriter<W: Write, B: Read + Write + Seek = Cursor<Vec<u8>>> {
+    writer: PackageWriter<B>,
+    out: W
+}
+
+impl<W: Write> PackageStreamWriter<W> {
+    pub fn new(out: W) -> Self {
+        PackageStreamWriter::with_buffer(out, Cursor::new(Vec::new()))
+    }
+}
+
+impl<W: Write, B: Read + Write + Seek> PackageStreamWriter<W, B> {
+    /// Buffers the archive in `buffer`, which should be empty.
+    pub fn with_buffer(out: W, buffer: B) -> Self {
+        PackageStreamWriter {
+            writer: PackageWriter::new(buffer),
+            out
+        }
+    }
+
+    /// Completes the archive in the buffer and copies it to the output.
+    ///
+    /// Returns the output, and the buffer for reuse or cleanup.
+    pub fn finalize(mut self) -> Result<(W, B)> {
+        let mut buffer = self.writer.finalize()?;
+
+        buffer.seek(SeekFrom::Start(0))?;
+        io::copy(&mut buffer, &mut self.out)?;
+        self.out.flush()?;
+
+        Ok((self.out, buffer))
+    }
+}
+
+impl<W: Write, B: Read + Write + Seek> Deref for PackageStreamWriter<W, B> {
+    type Target = PackageWriter<B>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.writer
+    }
+}
+
+impl<W: Write, B: Read + Write + Seek> DerefMut for PackageStreamWriter<W, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Package;
+
+    // accepts writes only, like a pipe
+    struct Pipe(Vec<u8>);
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_forward_only() {
+        let mut writer = PackageStreamWriter::new(Pipe(Vec::new()));
+        writer.add_entry("a.txt", b"through a pipe").unwrap();
+        writer.add_entry("b.bin", &[5; 20_000]).unwrap();
+        assert_eq!(writer.len(), 2);
+        let (pipe, buffer) = writer.finalize().unwrap();
+        assert_eq!(&pipe.0, buffer.get_ref());
+
+        let mut cursor = Cursor::new(pipe.0);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.read_text_entry("a.txt").unwrap(), "through a pipe");
+        assert_eq!(package.read_entry("b.bin").unwrap(), vec![5; 20_000]);
+    }
+}
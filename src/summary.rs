@@ -0,0 +1,95 @@
+use std::fmt;
+use std::io::{Seek, Read};
+
+use crate::{OwnedPackage, Package, Toc};
+
+/// Headline numbers of an archive, see `Toc::summary`.
+///
+/// Displays as e.g. `ADAT v9, 1423 entries, 210.4 MiB uncompressed / 64.1 MiB compressed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Summary {
+    pub version: u32,
+    pub entries: usize, // distinct names
+    pub uncompressed_size: u64,
+    pub compressed_size: u64
+}
+
+// bytes in binary units, with one decimal above plain bytes
+struct HumanSize(u64);
+
+impl fmt::Display for HumanSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{:.1} {}", size, UNITS[unit])
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = if self.entries == 1 { "entry" } else { "entries" };
+        write!(f, "ADAT v{}, {} {}, {} uncompressed / {} compressed", self.version, self.entries, noun,
+            HumanSize(self.uncompressed_size), HumanSize(self.compressed_size))
+    }
+}
+
+impl Toc {
+    pub fn summary(&self) -> Summary {
+        Summary {
+            version: self.version(),
+            entries: self.len(),
+            uncompressed_size: self.total_uncompressed_size(),
+            compressed_size: self.total_compressed_size()
+        }
+    }
+}
+
+impl fmt::Display for Toc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.summary().fmt(f)
+    }
+}
+
+impl<T: Read + Seek> fmt::Display for Package<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.summary().fmt(f)
+    }
+}
+
+impl<T: Read + Seek + 'static> fmt::Display for OwnedPackage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.summary().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package_with;
+
+    #[test]
+    fn displays_summary() {
+        assert_eq!(HumanSize(512).to_string(), "512 B");
+        assert_eq!(HumanSize(1536).to_string(), "1.5 KiB");
+        assert_eq!(HumanSize(220_600_000).to_string(), "210.4 MiB");
+
+        let mut cursor = package_with(&[("a.bin", &[0; 3000]), ("b.txt", b"stored")]);
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let summary = package.summary();
+        assert_eq!(summary.entries, 2);
+        assert_eq!(summary.uncompressed_size, 3006);
+        let compressed = HumanSize(summary.compressed_size).to_string();
+        assert_eq!(package.to_string(), format!("ADAT v9, 2 entries, 2.9 KiB uncompressed / {} compressed", compressed));
+    }
+}
@@ -0,0 +1,535 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Seek, Read, SeekFrom};
+use std::sync::Arc;
+
+use crate::{AdatError, Result, Codec, EntryInfo, EntryNames, Manifest, MountOptions, PackageHeader, PackageEntry, RejectedEntry};
+use crate::{fits_in_memory, ADAT_ENTRY_SIZE};
+use crate::glob::glob_match;
+use crate::index::NameIndex;
+
+/// The parsed header and table of contents of an archive.
+///
+/// Everything that can be answered without touching entry data lives here;
+/// the package types dereference to it. Always `Send` and `Sync`, since
+/// codecs have to be.
+#[derive(Debug)]
+pub struct Toc {
+    pub(crate) header: PackageHeader,
+    pub(crate) entries: Vec<PackageEntry>, // in TOC order, duplicates included
+    index: NameIndex, // name -> position in entries of the last entry with that name
+    folded: Option<HashMap<String, Vec<usize>>>, // lowercased name -> index positions, if case-insensitive
+    pub(crate) file_len: u64, // length of the archive when it was mounted
+    normalize_separators: bool, // names were indexed with `\` replaced by `/`, lookups get the same
+    pub(crate) codec: Arc<dyn Codec>
+}
+
+/// Which length `Toc::entries_sorted_by_size` sorts by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeKind {
+    Uncompressed,
+    Compressed // as stored in the archive
+}
+
+// what a name looked up or stored is indexed as, see MountOptions::normalize_separators
+fn normalized(name: &str, normalize: bool) -> Cow<'_, str> {
+    if normalize && name.contains('\\') {
+        Cow::Owned(name.replace('\\', "/"))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+// "a/b" and "a/b/" both name the directory "a/b/"; "" and "/" name the root
+fn directory_prefix(dir: &str) -> String {
+    let dir = dir.trim_end_matches('/');
+    if dir.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", dir)
+    }
+}
+
+impl Toc {
+    pub(crate) fn read_toc<T: Read + Seek>(cursor: &mut T, options: &MountOptions) -> Result<Self> {
+        Toc::read_toc_with(cursor, options, false).map(|(toc, _)| toc)
+    }
+
+    // when lenient, entries failing check_entry are left out and returned instead of failing the mount
+    pub(crate) fn read_toc_with<T: Read + Seek>(cursor: &mut T, options: &MountOptions, lenient: bool) -> Result<(Self, Vec<RejectedEntry>)> {
+        trace_span!("mount", lenient);
+        let file_len = cursor.seek(SeekFrom::End(0))?;
+        cursor.seek(SeekFrom::Start(0))?;
+
+        let header: PackageHeader = PackageHeader::read_package_header(cursor, &options.format)?;
+        let entry_count = Toc::check_header(&header, file_len, options)?;
+
+        cursor.seek(SeekFrom::Start(header.toc_offset as u64))?;
+        let entries = PackageEntry::read_package_entries(cursor, entry_count)?;
+
+        Toc::from_entries(header, entries, file_len, options, lenient)
+    }
+
+    // number of TOC entries the header declares, once it is known they fit in the file
+    pub(crate) fn check_header(header: &PackageHeader, file_len: u64, options: &MountOptions) -> Result<u32> {
+        if !header.toc_length.is_multiple_of(ADAT_ENTRY_SIZE) {
+            return Err(AdatError::TocMisaligned(header.toc_length));
+        }
+
+        let entry_count = header.toc_length / ADAT_ENTRY_SIZE;
+        if entry_count == 0 && !options.allow_empty {
+            return Err(AdatError::EmptyToc);
+        }
+        if let Some(limit) = options.limits.max_entries.filter(|&limit| entry_count > limit) {
+            return Err(AdatError::TooManyEntries { count: entry_count, limit });
+        }
+        trace_debug!(version = header.version, toc_offset = header.toc_offset, entry_count, file_len, "header parsed");
+
+        // before allocating anything for the entries, make sure they are actually there
+        if header.toc_offset as u64 + header.toc_length as u64 > file_len {
+            return Err(AdatError::TocOutOfBounds {
+                toc_offset: header.toc_offset,
+                toc_length: header.toc_length,
+                file_len
+            });
+        }
+
+        Ok(entry_count)
+    }
+
+    pub(crate) fn from_entries(header: PackageHeader, mut entries: Vec<PackageEntry>, file_len: u64, options: &MountOptions, lenient: bool) -> Result<(Self, Vec<RejectedEntry>)> {
+        for entry in &mut entries {
+            if let Cow::Owned(path) = normalized(&entry.path, options.normalize_separators) {
+                entry.path = path;
+            }
+        }
+
+        // a sorted index finds duplicates by sorting positions instead of hashing a copy of every name
+        let mut sorted: Vec<usize> = Vec::new();
+        let mut duplicates: Vec<bool> = Vec::new();
+        if options.sorted_index {
+            sorted = (0..entries.len()).filter(|&position| {
+                Toc::check_entry(&entries[position], &header, file_len, options, false).is_ok()
+            }).collect();
+            // stable, so entries of one name stay in TOC order
+            sorted.sort_by(|&a, &b| entries[a].path.cmp(&entries[b].path));
+
+            duplicates = vec![false; entries.len()];
+            for pair in sorted.windows(2) {
+                duplicates[pair[1]] = entries[pair[0]].path == entries[pair[1]].path;
+            }
+        }
+
+        let mut kept: Vec<PackageEntry> = Vec::with_capacity(entries.len());
+        let mut kept_at: Vec<usize> = Vec::new(); // position in kept of every entry, when sorted
+        let mut rejected: Vec<RejectedEntry> = Vec::new();
+        let mut hashed: HashMap<String, usize> = HashMap::new();
+        if options.sorted_index {
+            kept_at.reserve_exact(entries.len());
+        } else {
+            hashed.reserve(entries.len());
+        }
+
+        for (position, entry) in entries.into_iter().enumerate() {
+            let duplicate = match options.sorted_index {
+                true => duplicates[position],
+                false => hashed.contains_key(&entry.path)
+            };
+
+            match Toc::check_entry(&entry, &header, file_len, options, duplicate) {
+                Ok(()) => {
+                    // later entries shadow earlier ones of the same name, which stay in entries
+                    if options.sorted_index {
+                        kept_at.push(kept.len());
+                    } else {
+                        hashed.insert(entry.path.clone(), kept.len());
+                    }
+                    kept.push(entry);
+                }
+                Err(error) if lenient => {
+                    if options.sorted_index {
+                        kept_at.push(usize::MAX);
+                    }
+                    rejected.push(RejectedEntry { position, info: entry.info(), error })
+                }
+                Err(error) => return Err(error)
+            }
+        }
+
+        // only possible when lenient, check_header already looked at the entry count
+        if kept.is_empty() && !options.allow_empty {
+            return Err(AdatError::EmptyToc);
+        }
+        let entries = kept;
+
+        let index = if options.sorted_index {
+            // the last kept entry of every name, still in name order
+            sorted.retain(|&position| kept_at[position] != usize::MAX);
+            let mut positions: Vec<usize> = Vec::with_capacity(sorted.len());
+            for (i, &position) in sorted.iter().enumerate() {
+                let shadowed = sorted.get(i + 1).is_some_and(|&next| entries[kept_at[next]].path == entries[kept_at[position]].path);
+                if !shadowed {
+                    positions.push(kept_at[position]);
+                }
+            }
+            NameIndex::Sorted(positions)
+        } else {
+            NameIndex::Hashed(hashed)
+        };
+
+        let folded = options.case_insensitive.then(|| {
+            let mut folded: HashMap<String, Vec<usize>> = HashMap::with_capacity(index.len());
+            for (name, position) in index.iter(&entries) {
+                folded.entry(name.to_lowercase()).or_default().push(position);
+            }
+            folded
+        });
+
+        let toc = Toc {
+            header,
+            entries,
+            index,
+            folded,
+            file_len,
+            normalize_separators: options.normalize_separators,
+            codec: options.codec.clone()
+        };
+        trace_debug!(entries = toc.entries.len(), names = toc.len(), rejected = rejected.len(), "toc read");
+
+        Ok((toc, rejected))
+    }
+
+    // the checks an entry has to pass to be mounted; `duplicate` tells whether an
+    // entry of the same name was mounted before it
+    fn check_entry(entry: &PackageEntry, header: &PackageHeader, file_len: u64, options: &MountOptions, duplicate: bool) -> Result<()> {
+        if options.strict_names {
+            entry.get_name().map_err(AdatError::InvalidName)?;
+        }
+
+        let name_len = entry.name_bytes().len();
+        if options.reject_unterminated_names && !entry.is_name_terminated() {
+            return Err(AdatError::UnterminatedName(entry.path.clone()));
+        }
+        if let Some(limit) = options.limits.max_name_len.filter(|&limit| name_len > limit) {
+            return Err(AdatError::NameOverLimit { name: entry.path.clone(), length: name_len, limit });
+        }
+
+        // lengths are stored as u32, which is over what a 32-bit target can allocate
+        let size = entry.length.max(entry.compressed_length) as u64;
+        if !fits_in_memory(size) {
+            return Err(AdatError::EntryTooLarge { name: entry.path.clone(), length: size, limit: isize::MAX as u64 });
+        }
+
+        if entry.offset as u64 + entry.compressed_length as u64 > file_len {
+            return Err(AdatError::EntryOutOfBounds {
+                name: entry.path.clone(),
+                offset: entry.offset,
+                compressed_length: entry.compressed_length as u32,
+                file_len
+            });
+        }
+
+        if options.reject_toc_overlap && entry.overlaps(header.toc_offset, header.toc_length) {
+            return Err(AdatError::EntryOverlapsToc {
+                name: entry.path.clone(),
+                offset: entry.offset,
+                compressed_length: entry.compressed_length as u32,
+                toc_offset: header.toc_offset,
+                toc_length: header.toc_length
+            });
+        }
+
+        if options.reject_duplicates && duplicate {
+            return Err(AdatError::DuplicateEntry(entry.path.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Name under which the entry with the given exact stored name is indexed.
+    ///
+    /// Only differs from the stored name if that is not valid UTF-8.
+    pub fn find_by_name_bytes(&self, name_bytes: &[u8]) -> Option<&str> {
+        self.entries.iter().find(|pe| pe.name_bytes() == name_bytes).map(|pe| {
+            pe.path.as_str()
+        })
+    }
+
+    // position in entries of the entry a name resolves to
+    pub(crate) fn position(&self, entry_path: &str) -> Result<usize> {
+        let entry_path = &*normalized(entry_path, self.normalize_separators);
+        if let Some(position) = self.index.get(&self.entries, entry_path) {
+            return Ok(position);
+        }
+
+        match self.folded.as_ref().and_then(|folded| folded.get(&entry_path.to_lowercase())) {
+            Some(positions) if positions.len() == 1 => Ok(positions[0]),
+            Some(positions) => {
+                let mut candidates: Vec<String> = positions.iter().map(|&position| {
+                    self.entries[position].path.clone()
+                }).collect();
+                candidates.sort_unstable();
+
+                Err(AdatError::AmbiguousEntry { name: entry_path.to_string(), candidates })
+            }
+            None => Err(AdatError::EntryNotFound(entry_path.to_string()))
+        }
+    }
+
+    // position of the entry stored under exactly this name, without any case-insensitive fallback
+    pub(crate) fn exact_position(&self, entry_path: &str) -> Option<usize> {
+        self.index.get(&self.entries, &normalized(entry_path, self.normalize_separators))
+    }
+
+    pub(crate) fn lookup(&self, entry_path: &str) -> Result<&PackageEntry> {
+        self.position(entry_path).map(|position| &self.entries[position])
+    }
+
+    // the entry at a TOC position, if it may be read under a reader's max_decompressed_size
+    pub(crate) fn readable_entry_at(&self, position: usize, limit: Option<usize>) -> Result<&PackageEntry> {
+        let pe = self.entries.get(position).ok_or(AdatError::IndexOutOfBounds {
+            index: position,
+            len: self.entries.len()
+        })?;
+
+        pe.check_length(limit)?;
+        Ok(pe)
+    }
+
+    // the entry a name resolves to, if it may be read under a reader's max_decompressed_size
+    pub(crate) fn readable_entry(&self, entry_path: &str, limit: Option<usize>) -> Result<&PackageEntry> {
+        let pe = self.lookup(entry_path)?;
+        pe.check_length(limit)?;
+        Ok(pe)
+    }
+
+    // the entry a name resolves to
+    pub(crate) fn entry(&self, entry_path: &str) -> Option<&PackageEntry> {
+        self.lookup(entry_path).ok()
+    }
+
+    // positions of the entries lookups resolve to, in TOC order
+    pub(crate) fn reachable_positions(&self) -> Vec<usize> {
+        let mut positions: Vec<usize> = self.index.positions(&self.entries).collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// Names that occur more than once in the TOC.
+    ///
+    /// Lookups by such a name resolve to the last entry carrying it; the
+    /// shadowed ones are still listed by `entries_named`.
+    pub fn duplicate_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.entries.iter().enumerate().filter(|&(position, pe)| {
+            self.index.get(&self.entries, &pe.path) != Some(position)
+        }).map(|(_, pe)| pe.path.as_str()).collect();
+
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Names stored without a null terminator, filling all 128 bytes of the
+    /// name field, in TOC order. These are read as the full 128 bytes but may
+    /// be longer names cut off by the writer; see
+    /// `MountOptions::reject_unterminated_names`.
+    pub fn unterminated_names(&self) -> Vec<&str> {
+        self.entries.iter().filter(|pe| !pe.is_name_terminated()).map(|pe| pe.path.as_str()).collect()
+    }
+
+    /// All entries carrying the given name, in TOC order.
+    pub fn entries_named(&self, entry_path: &str) -> Vec<EntryInfo> {
+        let entry_path = normalized(entry_path, self.normalize_separators);
+        self.entries.iter().filter(|pe| pe.path == entry_path).map(|pe| pe.info()).collect()
+    }
+
+    /// Header version of the mounted archive.
+    pub fn version(&self) -> u32 {
+        self.header.version
+    }
+
+    /// Offset of the TOC in the archive, as declared by the header.
+    pub fn toc_offset(&self) -> u32 {
+        self.header.toc_offset
+    }
+
+    /// Length of the TOC in bytes, as declared by the header.
+    pub fn toc_length(&self) -> u32 {
+        self.header.toc_length
+    }
+
+    /// The header as stored: magic, TOC offset, TOC length and version,
+    /// each a little-endian u32.
+    pub fn header_bytes(&self) -> [u8; 16] {
+        self.header.to_bytes()
+    }
+
+    pub fn contains(&self, entry_path: &str) -> bool {
+        self.position(entry_path).is_ok()
+    }
+
+    /// Number of distinct entry names in the package.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.len() == 0
+    }
+
+    /// Allocating convenience over `entry_names`.
+    pub fn list_entries(&self) -> Vec<String> {
+        self.entry_names().map(|name| name.to_string()).collect()
+    }
+
+    pub fn entry_names(&self) -> EntryNames<'_> {
+        EntryNames { inner: self.index.iter(&self.entries) }
+    }
+
+    /// All names, borrowed and sorted; one allocation for the whole list.
+    pub fn collect_names_sorted(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.entry_names().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Names of all TOC entries in on-disk order, duplicates included.
+    pub fn entries_in_order(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries.iter().map(|pe| pe.path.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, EntryInfo)> + '_ {
+        self.index.iter(&self.entries).map(|(name, position)| (name, self.entries[position].info()))
+    }
+
+    pub fn entry_info(&self, entry_path: &str) -> Option<EntryInfo> {
+        self.entry(entry_path).map(|pe| pe.info())
+    }
+
+    pub fn entries_info(&self) -> Vec<EntryInfo> {
+        self.index.positions(&self.entries).map(|position| self.entries[position].info()).collect()
+    }
+
+    /// Entries reachable by name, largest first by the given length; entries
+    /// of the same size are ordered by name.
+    pub fn entries_sorted_by_size(&self, by: SizeKind) -> Vec<EntryInfo> {
+        let mut entries = self.entries_info();
+        entries.sort_unstable_by(|a, b| {
+            let (a_size, b_size) = match by {
+                SizeKind::Uncompressed => (a.length, b.length),
+                SizeKind::Compressed => (a.compressed_length, b.compressed_length)
+            };
+            b_size.cmp(&a_size).then_with(|| a.name.cmp(&b.name))
+        });
+        entries
+    }
+
+    /// Sum of the decompressed lengths of all entries reachable by name.
+    pub fn total_uncompressed_size(&self) -> u64 {
+        self.index.positions(&self.entries).map(|position| self.entries[position].length as u64).sum()
+    }
+
+    /// Sum of the compressed lengths of all entries reachable by name.
+    pub fn total_compressed_size(&self) -> u64 {
+        self.index.positions(&self.entries).map(|position| self.entries[position].compressed_length as u64).sum()
+    }
+
+    /// Compressed size as a fraction of the uncompressed size; 1.0 if there is nothing to compress.
+    pub fn compression_ratio(&self) -> f64 {
+        match self.total_uncompressed_size() {
+            0 => 1.0,
+            uncompressed => self.total_compressed_size() as f64 / uncompressed as f64
+        }
+    }
+
+    /// Names of the TOC entries whose unknown `u0` field holds `value`, in TOC order.
+    pub fn find_by_u0(&self, value: u32) -> Vec<&str> {
+        self.entries.iter().filter(|pe| pe.u0 == value).map(|pe| pe.path.as_str()).collect()
+    }
+
+    /// Names matching a glob pattern such as `some/path/*.txt`, sorted.
+    ///
+    /// `?` and `*` stay within one path component, `**` spans any number of them.
+    pub fn find(&self, pattern: &str) -> Vec<&str> {
+        self.filter(|name| glob_match(pattern, name))
+    }
+
+    /// Names whose last component ends in `.ext`, ignoring ASCII case, sorted.
+    ///
+    /// The extension is whatever follows the last `.` of the last component;
+    /// a leading `.` on `ext` is ignored, so `"png"` and `".PNG"` match alike.
+    pub fn entries_with_extension(&self, ext: &str) -> Vec<&str> {
+        let ext = ext.strip_prefix('.').unwrap_or(ext);
+        self.filter(|name| {
+            let file_name = name.rsplit('/').next().unwrap_or(name);
+            file_name.rsplit_once('.').is_some_and(|(_, found)| found.eq_ignore_ascii_case(ext))
+        })
+    }
+
+    /// Names for which `predicate` returns true, sorted.
+    pub fn filter<F: Fn(&str) -> bool>(&self, predicate: F) -> Vec<&str> {
+        let mut names: Vec<&str> = self.entry_names().filter(|name| predicate(name)).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Names inside the directory `prefix`, at any depth, sorted.
+    ///
+    /// A trailing `/` on `prefix` is optional; an empty prefix lists everything.
+    pub fn entries_under(&self, prefix: &str) -> Vec<&str> {
+        let dir = directory_prefix(prefix);
+        self.filter(|name| name.starts_with(dir.as_str()))
+    }
+
+    /// The immediate contents of the directory `dir`, sorted.
+    ///
+    /// Files are returned by their full name, subdirectories by their full
+    /// path with a trailing `/`, each only once.
+    pub fn children(&self, dir: &str) -> Vec<&str> {
+        let dir = directory_prefix(dir);
+        let mut children: Vec<&str> = self.entry_names().filter_map(|name| {
+            let rest = name.strip_prefix(dir.as_str())?;
+            match rest.find('/') {
+                Some(slash) => Some(&name[..dir.len() + slash + 1]),
+                None => Some(name)
+            }
+        }).collect();
+
+        children.sort_unstable();
+        children.dedup();
+        children
+    }
+
+    /// Number of names under each top-level directory, keyed by its name
+    /// without a trailing `/`. Names at the top level count under `""`.
+    pub fn prefix_counts(&self) -> HashMap<String, usize> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for name in self.entry_names() {
+            let prefix = name.split_once('/').map_or("", |(first, _)| first);
+            *counts.entry(prefix.to_string()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Header version and metadata of every TOC entry, in TOC order.
+    pub fn manifest(&self) -> Manifest {
+        Manifest {
+            version: self.header.version,
+            entries: self.entries.iter().map(|pe| pe.info()).collect()
+        }
+    }
+
+    /// Name of the entry at the given TOC position.
+    pub fn name_at(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(|pe| pe.path.as_str())
+    }
+}
+
+impl<'a> IntoIterator for &'a Toc {
+    type Item = &'a str;
+    type IntoIter = EntryNames<'a>;
+
+    fn into_iter(self) -> EntryNames<'a> {
+        self.entry_names()
+    }
+}
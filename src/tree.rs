@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use crate::{EntryInfo, Toc};
+
+/// A directory or file in the tree built from the `/`-separated entry names.
+///
+/// A node carries `info` if an entry is stored under its path, and children
+/// if other entries live below it; usually it is one or the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeNode {
+    pub name: String, // last path component, empty for the root
+    pub path: String, // full path from the root, without a trailing slash
+    pub info: Option<EntryInfo>,
+    pub children: Vec<TreeNode> // sorted by name
+}
+
+impl TreeNode {
+    pub fn is_dir(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// Looks up the node at a `/`-separated path below this one.
+    pub fn get(&self, path: &str) -> Option<&TreeNode> {
+        path.split('/').filter(|c| !c.is_empty()).try_fold(self, |node, component| {
+            node.children.iter().find(|child| child.name == component)
+        })
+    }
+}
+
+/// An item directly inside a directory, see `Toc::read_dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String, // last path component
+    pub path: String, // full path from the root, without a trailing slash
+    pub info: Option<EntryInfo> // the entry stored under path; None for a directory
+}
+
+impl DirEntry {
+    pub fn is_dir(&self) -> bool {
+        self.info.is_none()
+    }
+}
+
+// intermediate form, so shared directories are merged by name
+#[derive(Default)]
+struct Builder {
+    info: Option<EntryInfo>,
+    children: BTreeMap<String, Builder>
+}
+
+impl Builder {
+    fn into_node(self, name: String, path: String) -> TreeNode {
+        let children = self.children.into_iter().map(|(child, builder)| {
+            let child_path = if path.is_empty() { child.clone() } else { format!("{}/{}", path, child) };
+            builder.into_node(child, child_path)
+        }).collect();
+
+        TreeNode { name, path, info: self.info, children }
+    }
+}
+
+impl Toc {
+    /// Nested view of all entries, with directories derived from the names.
+    pub fn tree(&self) -> TreeNode {
+        let mut root = Builder::default();
+
+        for (name, info) in self.iter() {
+            let node = name.split('/').filter(|c| !c.is_empty()).fold(&mut root, |node, component| {
+                node.children.entry(component.to_string()).or_default()
+            });
+            node.info = Some(info);
+        }
+
+        root.into_node(String::new(), String::new())
+    }
+
+    /// The files and directories directly inside `dir`, sorted by name; an
+    /// empty `dir` is the root.
+    ///
+    /// Entries further down are collapsed into the directory containing them.
+    /// A name that is both an entry and the directory of others is listed twice,
+    /// once as each.
+    pub fn read_dir(&self, dir: &str) -> Vec<DirEntry> {
+        self.children(dir).into_iter().map(|child| {
+            let (path, info) = match child.strip_suffix('/') {
+                Some(path) => (path, None),
+                None => (child, self.entry_info(child))
+            };
+            let name = path.rsplit('/').next().unwrap_or(path);
+
+            DirEntry { name: name.to_string(), path: path.to_string(), info }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{package_with, Package};
+
+    #[test]
+    fn builds_tree() {
+        let names = ["some/path/foo.txt", "some/path/bar.txt", "some/other.bin", "top.txt"];
+        let mut cursor = package_with(&names.map(|name| (name, name.as_bytes())));
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let tree = package.tree();
+        let names: Vec<&str> = tree.children.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["some", "top.txt"]);
+
+        let some = tree.get("some").unwrap();
+        assert!(some.is_dir());
+        assert_eq!(some.info, None);
+        assert_eq!(some.children.len(), 2);
+
+        let path = tree.get("some/path").unwrap();
+        let files: Vec<&str> = path.children.iter().map(|node| node.path.as_str()).collect();
+        assert_eq!(files, vec!["some/path/bar.txt", "some/path/foo.txt"]);
+
+        let foo = tree.get("some/path/foo.txt").unwrap();
+        assert!(!foo.is_dir());
+        assert_eq!(foo.info, package.entry_info("some/path/foo.txt"));
+        assert_eq!(tree.get("some/missing"), None);
+    }
+
+    #[test]
+    fn reads_directories() {
+        let names = ["some/path/foo.txt", "some/path/deeper/x.bin", "some/other.bin", "top.txt"];
+        let mut cursor = package_with(&names.map(|name| (name, name.as_bytes())));
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let root = package.read_dir("");
+        let listed: Vec<(&str, bool)> = root.iter().map(|e| (e.name.as_str(), e.is_dir())).collect();
+        assert_eq!(listed, vec![("some", true), ("top.txt", false)]);
+
+        let path = package.read_dir("some/path/");
+        let listed: Vec<(&str, bool)> = path.iter().map(|e| (e.path.as_str(), e.is_dir())).collect();
+        assert_eq!(listed, vec![("some/path/deeper", true), ("some/path/foo.txt", false)]);
+        assert_eq!(path[1].info, package.entry_info("some/path/foo.txt"));
+        assert_eq!(path[1].name, "foo.txt");
+
+        assert!(package.read_dir("missing").is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt::Debug;
+use std::io::{Cursor, Read, Seek};
+
+use vfs::{FileSystem, SeekAndRead, SeekAndWrite, VfsError, VfsFileType, VfsMetadata, VfsResult};
+use vfs::error::VfsErrorKind;
+
+use crate::{AdatError, SharedPackage, Toc};
+
+// vfs hands out paths with a leading slash, the root being empty
+fn entry_path(path: &str) -> &str {
+    path.trim_start_matches('/')
+}
+
+fn vfs_error(e: AdatError) -> VfsError {
+    match e.without_context() {
+        AdatError::EntryNotFound(_) => VfsErrorKind::FileNotFound.into(),
+        _ => VfsErrorKind::IoError(e.into()).into()
+    }
+}
+
+fn read_only<T>() -> VfsResult<T> {
+    Err(VfsErrorKind::NotSupported.into())
+}
+
+impl<T: Read + Seek> SharedPackage<T> {
+    // the root always exists, other directories only as prefixes of entry names
+    fn is_dir(&self, path: &str) -> bool {
+        path.is_empty() || !self.children(path).is_empty()
+    }
+}
+
+/// A read-only filesystem over the entries, with directories derived from
+/// the `/`-separated names as in `Toc::read_dir`. Files are decompressed
+/// whole when opened.
+impl<T: Read + Seek + Send + Debug + 'static> FileSystem for SharedPackage<T> {
+    fn read_dir(&self, path: &str) -> VfsResult<Box<dyn Iterator<Item = String> + Send>> {
+        let path = entry_path(path);
+        if !self.is_dir(path) {
+            return Err(VfsErrorKind::FileNotFound.into());
+        }
+
+        let names: Vec<String> = Toc::read_dir(self, path).into_iter().map(|dir_entry| dir_entry.name).collect();
+        Ok(Box::new(names.into_iter()))
+    }
+
+    fn create_dir(&self, _path: &str) -> VfsResult<()> {
+        read_only()
+    }
+
+    fn open_file(&self, path: &str) -> VfsResult<Box<dyn SeekAndRead + Send>> {
+        let data = self.read_entry(entry_path(path)).map_err(vfs_error)?;
+        Ok(Box::new(Cursor::new(data)))
+    }
+
+    fn create_file(&self, _path: &str) -> VfsResult<Box<dyn SeekAndWrite + Send>> {
+        read_only()
+    }
+
+    fn append_file(&self, _path: &str) -> VfsResult<Box<dyn SeekAndWrite + Send>> {
+        read_only()
+    }
+
+    fn metadata(&self, path: &str) -> VfsResult<VfsMetadata> {
+        let path = entry_path(path);
+        let (file_type, len) = match self.entry_info(path) {
+            Some(info) => (VfsFileType::File, info.length as u64),
+            None if self.is_dir(path) => (VfsFileType::Directory, 0),
+            None => return Err(VfsErrorKind::FileNotFound.into())
+        };
+
+        Ok(VfsMetadata { file_type, len, created: None, modified: None, accessed: None })
+    }
+
+    fn exists(&self, path: &str) -> VfsResult<bool> {
+        let path = entry_path(path);
+        Ok(self.contains(path) || self.is_dir(path))
+    }
+
+    fn remove_file(&self, _path: &str) -> VfsResult<()> {
+        read_only()
+    }
+
+    fn remove_dir(&self, _path: &str) -> VfsResult<()> {
+        read_only()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{build_archive, SharedPackage};
+    use std::collections::HashMap;
+    use std::io::{Cursor, Read};
+    use vfs::{VfsFileType, VfsPath};
+
+    #[test]
+    fn serves_entries_as_files() {
+        let entries: HashMap<&str, &[u8]> = HashMap::from([("docs/readme.txt", b"read me".as_slice()), ("top.bin", &[3; 2000])]);
+        let package = SharedPackage::mount(Cursor::new(build_archive(&entries).unwrap())).unwrap();
+        let root = VfsPath::new(package);
+
+        let mut names: Vec<String> = root.read_dir().unwrap().map(|path| path.filename()).collect();
+        names.sort();
+        assert_eq!(names, vec!["docs", "top.bin"]);
+
+        let readme = root.join("docs/readme.txt").unwrap();
+        let mut text = String::new();
+        readme.open_file().unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "read me");
+        assert_eq!(readme.metadata().unwrap().len, 7);
+
+        assert_eq!(root.join("docs").unwrap().metadata().unwrap().file_type, VfsFileType::Directory);
+        assert!(root.join("top.bin").unwrap().is_file().unwrap());
+        assert!(!root.join("missing").unwrap().exists().unwrap());
+        assert!(root.join("missing").unwrap().open_file().is_err());
+        assert!(root.join("new.txt").unwrap().create_file().is_err());
+    }
+}
@@ -0,0 +1,622 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Seek, Read, Write, SeekFrom};
+use std::path::Path;
+
+use miniz_oxide::deflate::{compress_to_vec_zlib, CompressionLevel};
+use sha2::{Digest, Sha256};
+
+use crate::{AdatError, Result, MountOptions, PackageHeader, PackageEntry, Toc};
+use crate::{ADAT_MAGIC, ADAT_VERSION, ADAT_HEADER_SIZE, ADAT_ENTRY_SIZE};
+
+/// Builds an ADAT archive: header, then the compressed blobs, then the TOC.
+///
+/// Blobs are written as they are added; the TOC and the header are only
+/// written by `finalize`, once all offsets are known.
+#[derive(Debug)]
+pub struct PackageWriter<W: Write + Seek> {
+    cursor: W,
+    entries: Vec<PackageEntry>,
+    names: HashSet<String>,
+    data_end: u32, // offset right after the last written blob
+    replace_existing: bool,
+    compression_level: u8,
+    alignment: u32, // blobs start at multiples of it
+    payloads: Option<HashMap<PayloadKey, u32>> // offsets of the blobs written so far, when deduplicating
+}
+
+// identifies a blob by its length and SHA-256 digest, strong enough to stand in for its bytes
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct PayloadKey {
+    length: usize,
+    digest: [u8; 32]
+}
+
+impl PayloadKey {
+    fn new(blob: &[u8]) -> Self {
+        PayloadKey { length: blob.len(), digest: Sha256::digest(blob).into() }
+    }
+}
+
+// raw name field into which the entry name is zero-padded
+fn name_field(name: &str) -> Result<[u8; 128]> {
+    let mut field: [u8; 128] = [0; 128];
+    let bytes = name.as_bytes();
+
+    if bytes.is_empty() || bytes.contains(&0) {
+        return Err(AdatError::InvalidEntryName(name.to_string()));
+    }
+    if bytes.len() > field.len() {
+        return Err(AdatError::NameTooLong(name.to_string()));
+    }
+
+    field[..bytes.len()].copy_from_slice(bytes);
+    Ok(field)
+}
+
+pub(crate) const DEFAULT_COMPRESSION_LEVEL: u8 = CompressionLevel::BestCompression as u8;
+
+pub(crate) fn compress(data: &[u8], level: u8) -> Vec<u8> {
+    compress_to_vec_zlib(data, level)
+}
+
+// the bytes to store for an entry: compressed, or the data as is where compression
+// does not pay off, which readers tell by equal lengths; see decode_entry
+pub(crate) fn encode_entry(data: &[u8], level: u8) -> Cow<'_, [u8]> {
+    let compressed = compress(data, level.min(CompressionLevel::UberCompression as u8));
+    if compressed.len() >= data.len() {
+        Cow::Borrowed(data)
+    } else {
+        Cow::Owned(compressed)
+    }
+}
+
+// offsets and lengths are stored as u32 on disk
+fn to_u32(value: u64, what: &'static str) -> Result<u32> {
+    u32::try_from(value).map_err(|_| {
+        AdatError::SizeOverflow { what, value }
+    })
+}
+
+// regular files below `dir`; symlinks are not followed, so a link back to an
+// ancestor cannot send the walk round in circles
+fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let file_type = dir_entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&dir_entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(dir_entry.path());
+        }
+    }
+    Ok(())
+}
+
+// `/`-joined entry name for a relative path, whatever the platform separator
+fn entry_name_for(relative: &Path) -> Result<String> {
+    let mut components: Vec<&str> = Vec::new();
+    for component in relative.components() {
+        let component = component.as_os_str().to_str().ok_or_else(|| {
+            AdatError::InvalidEntryName(relative.to_string_lossy().into_owned())
+        })?;
+        components.push(component);
+    }
+    Ok(components.join("/").replace('\\', "/"))
+}
+
+impl<W: Write + Seek> PackageWriter<W> {
+    pub fn new(cursor: W) -> Self {
+        PackageWriter {
+            cursor,
+            entries: Vec::new(),
+            names: HashSet::new(),
+            data_end: ADAT_HEADER_SIZE,
+            replace_existing: false,
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            alignment: 1,
+            payloads: None
+        }
+    }
+
+    /// zlib level for entries added from now on, from 0 (stored) to 10
+    /// (slowest); higher values are clamped. Defaults to 9.
+    pub fn set_compression_level(&mut self, level: u8) {
+        self.compression_level = level.min(CompressionLevel::UberCompression as u8);
+    }
+
+    pub fn compression_level(&self) -> u8 {
+        self.compression_level
+    }
+
+    /// Starts the data of entries added from now on at offsets that are a
+    /// multiple of `bytes`, e.g. 16 or 512, zero-padding the space before
+    /// them. 0 and 1 both mean no alignment, the default.
+    pub fn set_alignment(&mut self, bytes: u32) {
+        self.alignment = bytes.max(1);
+    }
+
+    pub fn alignment(&self) -> u32 {
+        self.alignment
+    }
+
+    /// Whether adding an entry under a name that is already taken replaces
+    /// the earlier entry instead of failing with `DuplicateEntry`.
+    ///
+    /// Mostly useful with `append`; the replaced data stays in the file as dead space.
+    pub fn replace_existing(&mut self, replace: bool) {
+        self.replace_existing = replace;
+    }
+
+    /// Whether entries added from now on whose compressed data is identical
+    /// to that of an entry added before share its blob, instead of storing
+    /// the bytes again. Blobs are matched by their length and SHA-256 digest,
+    /// so the output is never read back.
+    pub fn deduplicate(&mut self, deduplicate: bool) {
+        self.payloads = deduplicate.then(|| self.payloads.take().unwrap_or_default());
+    }
+
+    pub fn add_entry(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.add_entry_with_level(name, data, self.compression_level)
+    }
+
+    /// Like `add_entry`, but compresses at `level` instead of the writer's level,
+    /// e.g. 0 for media that is compressed already.
+    pub fn add_entry_with_level(&mut self, name: &str, data: &[u8], level: u8) -> Result<()> {
+        let field = name_field(name)?;
+        if self.names.contains(name) && !self.replace_existing {
+            return Err(AdatError::DuplicateEntry(name.to_string()));
+        }
+
+        let compressed = encode_entry(data, level);
+        to_u32(data.len() as u64, "entry length")?;
+
+        let key = self.payloads.is_some().then(|| PayloadKey::new(&compressed));
+        let offset = match key.as_ref().and_then(|key| self.payloads.as_ref()?.get(key).copied()) {
+            Some(offset) => offset,
+            None => {
+                let offset = self.write_blob(&compressed)?;
+                if let (Some(payloads), Some(key)) = (self.payloads.as_mut(), key) {
+                    payloads.insert(key, offset);
+                }
+                offset
+            }
+        };
+
+        if self.names.contains(name) {
+            self.entries.retain(|pe| pe.path != name);
+        }
+        self.entries.push(PackageEntry {
+            path: name.to_string(),
+            name: field,
+            offset,
+            length: data.len(),
+            compressed_length: compressed.len(),
+            u0: 0
+        });
+        self.names.insert(name.to_string());
+
+        Ok(())
+    }
+
+    /// Adds every file below `root`, recursively, named by its path relative
+    /// to `root` with `/` separators. Returns the number of files added.
+    ///
+    /// Symbolic links below `root` are skipped, whether they point to files or
+    /// directories; `root` itself may be one.
+    ///
+    /// Fails on the first name that is not UTF-8 or longer than 128 bytes.
+    pub fn add_dir(&mut self, root: impl AsRef<Path>) -> Result<usize> {
+        let root = root.as_ref();
+        let mut files = Vec::new();
+        collect_files(root, &mut files)?;
+        files.sort();
+
+        for file in &files {
+            let relative = file.strip_prefix(root).unwrap_or(file);
+            let name = entry_name_for(relative)?;
+            self.add_entry(&name, &fs::read(file)?)?;
+        }
+
+        Ok(files.len())
+    }
+
+    // copies an entry of another archive, name field and unknown fields included;
+    // duplicate names are kept as they are
+    pub(crate) fn add_raw_entry(&mut self, source: &PackageEntry, compressed_data: &[u8]) -> Result<()> {
+        let offset = self.write_blob(compressed_data)?;
+
+        self.entries.push(PackageEntry {
+            path: source.path.clone(),
+            name: source.name,
+            offset,
+            length: source.length,
+            compressed_length: compressed_data.len(),
+            u0: source.u0
+        });
+        self.names.insert(source.path.clone());
+
+        Ok(())
+    }
+
+    // writes a blob after the last one, padded to the alignment, returning its offset
+    fn write_blob(&mut self, blob: &[u8]) -> Result<u32> {
+        let offset = to_u32((self.data_end as u64).next_multiple_of(self.alignment as u64), "archive data length")?;
+        let data_end = to_u32(offset as u64 + blob.len() as u64, "archive data length")?;
+
+        self.cursor.seek(SeekFrom::Start(self.data_end as u64))?;
+        io::copy(&mut io::repeat(0).take((offset - self.data_end) as u64), &mut self.cursor)?;
+        self.cursor.write_all(blob)?;
+
+        self.data_end = data_end;
+        Ok(offset)
+    }
+
+    /// Number of entries added so far, including any kept from an appended archive.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the TOC after the blobs, then points the header at it.
+    ///
+    /// The header is written last, so when appending, the archive keeps
+    /// describing its previous contents until the new TOC is complete.
+    pub fn finalize(mut self) -> Result<W> {
+        let toc_length = to_u32(self.entries.len() as u64 * ADAT_ENTRY_SIZE as u64, "toc length")?;
+        to_u32(self.data_end as u64 + toc_length as u64, "archive length")?;
+
+        self.cursor.seek(SeekFrom::Start(self.data_end as u64))?;
+        for entry in &self.entries {
+            entry.write_package_entry(&mut self.cursor)?;
+        }
+
+        let header = PackageHeader {
+            magic: u32::from_le_bytes(ADAT_MAGIC),
+            toc_offset: self.data_end,
+            toc_length,
+            version: ADAT_VERSION
+        };
+        self.cursor.seek(SeekFrom::Start(0))?;
+        header.write_package_header(&mut self.cursor)?;
+        self.cursor.flush()?;
+
+        Ok(self.cursor)
+    }
+}
+
+impl<W: Read + Write + Seek> PackageWriter<W> {
+    /// Opens an existing archive to add entries to it.
+    ///
+    /// Its entries are kept as they are; new blobs go after the current end
+    /// of the file and `finalize` writes a new TOC after those. Archives
+    /// without any entries can be appended to as well.
+    pub fn append(mut cursor: W) -> Result<Self> {
+        let toc = Toc::read_toc(&mut cursor, &MountOptions::new().allow_empty(true))?;
+        let file_len = cursor.seek(SeekFrom::End(0))?;
+
+        Ok(PackageWriter {
+            names: toc.entries.iter().map(|pe| pe.path.clone()).collect(),
+            entries: toc.entries,
+            data_end: to_u32(file_len, "archive data length")?,
+            cursor,
+            replace_existing: false,
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            alignment: 1,
+            payloads: None
+        })
+    }
+}
+
+/// Builds a complete archive in memory from entry names and contents, e.g.
+/// to generate fixtures in tests instead of checking in binary files.
+///
+/// The layout is that of every archive `PackageWriter` produces: the 16 byte
+/// header (magic, TOC offset, TOC length, version), the zlib compressed
+/// contents back to back, then the TOC with one 144 byte record per entry
+/// (the zero-padded name in 128 bytes, then offset, decompressed length,
+/// compressed length and an unknown field, as little-endian u32). Entries
+/// are written in name order, so the same map always gives the same bytes.
+pub fn build_archive(entries: &HashMap<&str, &[u8]>) -> Result<Vec<u8>> {
+    let mut entries: Vec<(&str, &[u8])> = entries.iter().map(|(&name, &data)| (name, data)).collect();
+    entries.sort_by_key(|&(name, _)| name);
+    archive_of(&entries)
+}
+
+// an archive holding the entries in the given order
+fn archive_of(entries: &[(&str, &[u8])]) -> Result<Vec<u8>> {
+    let mut writer = PackageWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        writer.add_entry(name, data)?;
+    }
+    Ok(writer.finalize()?.into_inner())
+}
+
+// the fixture tests mount: an archive of the entries in TOC order, at the default level
+#[cfg(test)]
+pub(crate) fn package_with(entries: &[(&str, &[u8])]) -> std::io::Cursor<Vec<u8>> {
+    std::io::Cursor::new(archive_of(entries).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Package;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn round_trip() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("some/path/foo.txt", b"hello world from a test file!").unwrap();
+        writer.add_entry("bar.bin", &[7; 4096]).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.version(), 9);
+
+        let mut names = package.list_entries();
+        names.sort();
+        assert_eq!(names, vec!["bar.bin", "some/path/foo.txt"]);
+
+        assert_eq!(package.read_text_entry("some/path/foo.txt").unwrap(), "hello world from a test file!");
+        assert_eq!(package.read_entry("bar.bin").unwrap(), vec![7; 4096]);
+    }
+
+    #[test]
+    fn builds_fixtures() {
+        let entries: HashMap<&str, &[u8]> = HashMap::from([("b/c.txt", b"see".as_slice()), ("a.bin", &[1; 300])]);
+        let archive = build_archive(&entries).unwrap();
+        assert_eq!(archive, build_archive(&entries).unwrap());
+        assert!(crate::is_adat(&archive));
+
+        let mut cursor = Cursor::new(archive);
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["a.bin", "b/c.txt"]);
+        assert_eq!(package.read_text_entry("b/c.txt").unwrap(), "see");
+        assert_eq!(package.read_entry("a.bin").unwrap(), vec![1; 300]);
+
+        assert!(matches!(build_archive(&HashMap::from([("", b"".as_slice())])), Err(AdatError::InvalidEntryName(_))));
+    }
+
+    #[test]
+    fn round_trip_into_reused_buffer() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("big.bin", &[1; 100_000]).unwrap();
+        writer.add_entry("small.txt", b"small").unwrap();
+        writer.add_entry("empty", b"").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(package.read_entry_into("big.bin", &mut out).unwrap(), 100_000);
+        assert_eq!(out, vec![1; 100_000]);
+        let capacity = out.capacity();
+
+        assert_eq!(package.read_entry_into("small.txt", &mut out).unwrap(), 5);
+        assert_eq!(out, b"small");
+        assert_eq!(out.capacity(), capacity);
+
+        assert_eq!(package.read_entry_into("empty", &mut out).unwrap(), 0);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn appends_to_existing_archive() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("old.txt", b"old").unwrap();
+        writer.add_entry("replaced.txt", b"before").unwrap();
+        let cursor = writer.finalize().unwrap();
+        let original = cursor.get_ref().clone();
+
+        let mut writer = PackageWriter::append(cursor).unwrap();
+        writer.add_entry("new.txt", b"new").unwrap();
+        assert!(matches!(writer.add_entry("old.txt", b"again"), Err(AdatError::DuplicateEntry(_))));
+        writer.replace_existing(true);
+        writer.add_entry("replaced.txt", b"after").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+
+        // everything that was there is left in place, but the header
+        assert_eq!(&cursor.get_ref()[16..original.len()], &original[16..]);
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.entries_in_order().collect::<Vec<_>>(), vec!["old.txt", "new.txt", "replaced.txt"]);
+        assert_eq!(package.read_text_entry("old.txt").unwrap(), "old");
+        assert_eq!(package.read_text_entry("new.txt").unwrap(), "new");
+        assert_eq!(package.read_text_entry("replaced.txt").unwrap(), "after");
+    }
+
+    #[test]
+    fn deduplicates_payloads() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.deduplicate(true);
+        writer.add_entry("a.bin", &[7; 10_000]).unwrap();
+        writer.add_entry("b.txt", b"something else").unwrap();
+        writer.add_entry("copy/a.bin", &[7; 10_000]).unwrap();
+        writer.add_entry("copy/b.txt", b"something else").unwrap();
+        writer.add_entry("similar.txt", b"something ELSE").unwrap();
+        writer.deduplicate(false);
+        writer.add_entry("c.bin", &[7; 10_000]).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        let (a, copy) = (package.entry_info("a.bin").unwrap(), package.entry_info("copy/a.bin").unwrap());
+        assert_eq!((a.offset, a.compressed_length), (copy.offset, copy.compressed_length));
+        assert_eq!(package.entry_info("b.txt").unwrap().offset, package.entry_info("copy/b.txt").unwrap().offset);
+        assert_ne!(package.entry_info("c.bin").unwrap().offset, a.offset);
+        assert_ne!(package.entry_info("similar.txt").unwrap().offset, package.entry_info("b.txt").unwrap().offset);
+
+        // data region holds a.bin, b.txt, similar.txt and c.bin only
+        let blobs = 2 * a.compressed_length + 2 * package.entry_info("b.txt").unwrap().compressed_length;
+        assert_eq!(package.toc_offset(), 16 + blobs);
+        assert_eq!(package.read_entry("copy/a.bin").unwrap(), vec![7; 10_000]);
+        assert_eq!(package.read_text_entry("copy/b.txt").unwrap(), "something else");
+        assert!(package.verify().is_ok());
+    }
+
+    #[test]
+    fn deduplicates_into_write_only_file() {
+        let path = std::env::temp_dir().join(format!("adat-format-dedup-{}.dat", std::process::id()));
+        let mut writer = PackageWriter::new(fs::File::create(&path).unwrap());
+        writer.deduplicate(true);
+        writer.add_entry("a.bin", &[7; 10_000]).unwrap();
+        writer.add_entry("copy.bin", &[7; 10_000]).unwrap();
+        writer.finalize().unwrap();
+
+        let mut file = fs::File::open(&path).unwrap();
+        let mut package = Package::mount_from_cursor(&mut file).unwrap();
+        assert_eq!(package.entry_info("a.bin").unwrap().offset, package.entry_info("copy.bin").unwrap().offset);
+        assert_eq!(package.read_entry("copy.bin").unwrap(), vec![7; 10_000]);
+        drop(package);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn fills_placeholder_archive() {
+        let placeholder = PackageWriter::new(Cursor::new(Vec::new())).finalize().unwrap();
+        assert_eq!(placeholder.get_ref().len(), 16);
+
+        let mut writer = PackageWriter::append(placeholder).unwrap();
+        assert!(writer.is_empty());
+        writer.add_entry("later.txt", b"filled in").unwrap();
+        let mut cursor = writer.finalize().unwrap();
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.read_text_entry("later.txt").unwrap(), "filled in");
+    }
+
+    #[test]
+    fn adds_directory_tree() {
+        let root = std::env::temp_dir().join(format!("adat-format-add-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("some/path")).unwrap();
+        fs::write(root.join("top.txt"), b"top").unwrap();
+        fs::write(root.join("some/path/foo.txt"), b"foo").unwrap();
+
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        assert_eq!(writer.add_dir(&root).unwrap(), 2);
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.read_text_entry("some/path/foo.txt").unwrap(), "foo");
+        assert_eq!(package.read_text_entry("top.txt").unwrap(), "top");
+
+        fs::write(root.join("a".repeat(129)), b"").unwrap();
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        assert!(matches!(writer.add_dir(&root), Err(AdatError::NameTooLong(_))));
+
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(entry_name_for(Path::new("dir\\file.txt")).unwrap(), "dir/file.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinks_when_adding_directory() {
+        let root = std::env::temp_dir().join(format!("adat-format-add-dir-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/file.txt"), b"file").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("sub/file.txt"), root.join("link.txt")).unwrap();
+
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        assert_eq!(writer.add_dir(&root).unwrap(), 1);
+        let mut cursor = writer.finalize().unwrap();
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.list_entries(), vec!["sub/file.txt"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn honours_compression_level() {
+        let data: Vec<u8> = (0..50_000u32).map(|i| (i * 7 % 13) as u8 ^ (i / 1000) as u8).collect();
+        let compressed_size = |level: u8| {
+            let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+            writer.set_compression_level(level);
+            writer.add_entry("data.bin", &data).unwrap();
+            let mut cursor = writer.finalize().unwrap();
+            let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+            assert_eq!(package.read_entry("data.bin").unwrap(), data);
+            package.entry_info("data.bin").unwrap().compressed_length
+        };
+
+        let stored = compressed_size(0);
+        let best = compressed_size(9);
+        assert_eq!(stored as usize, data.len());
+        assert!(best < stored / 4);
+
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        assert_eq!(writer.compression_level(), 9);
+        writer.set_compression_level(200);
+        assert_eq!(writer.compression_level(), 10);
+        writer.add_entry_with_level("media.bin", &data, 0).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.entry_info("media.bin").unwrap().compressed_length, stored);
+    }
+
+    #[test]
+    fn stores_incompressible_entries() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("tiny.txt", b"abc").unwrap();
+        writer.add_entry("big.txt", &[b'a'; 1000]).unwrap();
+        let mut cursor = writer.finalize().unwrap();
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+
+        let tiny = package.entry_info("tiny.txt").unwrap();
+        assert_eq!(tiny.compressed_length, 3);
+        assert!(tiny.is_stored());
+        assert!(!package.entry_info("big.txt").unwrap().is_stored());
+        assert_eq!(package.read_entry_raw("tiny.txt").unwrap(), b"abc");
+        assert_eq!(package.read_text_entry("tiny.txt").unwrap(), "abc");
+        assert!(package.entry_info("big.txt").unwrap().compressed_length < 1000);
+
+        let mut streamed = String::new();
+        package.open_entry("tiny.txt").unwrap().read_to_string(&mut streamed).unwrap();
+        assert_eq!(streamed, "abc");
+    }
+
+    #[test]
+    fn aligns_entry_data() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+        writer.add_entry("unaligned.txt", b"first").unwrap();
+        writer.set_alignment(512);
+        writer.deduplicate(true);
+        for (i, size) in [1, 700, 0, 3000].into_iter().enumerate() {
+            writer.add_entry(&format!("entry{}.bin", i), &vec![i as u8; size]).unwrap();
+        }
+        writer.add_entry("copy.bin", &[3; 3000]).unwrap();
+        assert_eq!(writer.alignment(), 512);
+        let mut cursor = writer.finalize().unwrap();
+
+        let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+        assert_eq!(package.entry_info("unaligned.txt").unwrap().offset, 16);
+        for name in ["entry0.bin", "entry1.bin", "entry2.bin", "entry3.bin", "copy.bin"] {
+            assert_eq!(package.entry_info(name).unwrap().offset % 512, 0, "{}", name);
+        }
+        assert_eq!(package.entry_info("copy.bin").unwrap().offset, package.entry_info("entry3.bin").unwrap().offset);
+        assert_eq!(package.read_entry("entry1.bin").unwrap(), vec![1; 700]);
+        assert!(package.verify().is_ok());
+
+        // the gaps are nothing but zero padding
+        let gaps = package.layout_report().gaps;
+        drop(package);
+        assert!(!gaps.is_empty());
+        assert!(gaps.into_iter().flatten().all(|at| cursor.get_ref()[at as usize] == 0));
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()));
+
+        assert!(matches!(writer.add_entry(&"a".repeat(129), b""), Err(AdatError::NameTooLong(_))));
+        assert!(matches!(writer.add_entry("", b""), Err(AdatError::InvalidEntryName(_))));
+        assert!(matches!(writer.add_entry("a\0b", b""), Err(AdatError::InvalidEntryName(_))));
+
+        writer.add_entry(&"a".repeat(128), b"").unwrap();
+        assert!(matches!(writer.add_entry(&"a".repeat(128), b""), Err(AdatError::DuplicateEntry(_))));
+    }
+}
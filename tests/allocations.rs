@@ -0,0 +1,69 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use adat_format::{build_archive, Package};
+
+// counts every allocation, so a loop of reads can be checked for what it allocates
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn reuses_read_buffers() {
+    let names: Vec<String> = (0..20).map(|i| format!("entry{}.bin", i)).collect();
+    let contents: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; 10_000]).collect();
+    let mut entries: HashMap<&str, &[u8]> = names.iter().map(String::as_str).zip(contents.iter().map(Vec::as_slice)).collect();
+    entries.insert("stored.bin", &[0x5a]);
+    let mut cursor = Cursor::new(build_archive(&entries).unwrap());
+
+    let mut package = Package::mount_from_cursor(&mut cursor).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    for name in &names {
+        package.read_entry_into(name, &mut out).unwrap();
+    }
+
+    // nothing but the inflate state, one per compressed entry
+    let allocations = allocations_during(|| {
+        for _ in 0..10 {
+            for name in &names {
+                package.read_entry_into(name, &mut out).unwrap();
+            }
+        }
+    });
+    assert!(allocations <= 10 * names.len(), "{} allocations", allocations);
+
+    let allocations = allocations_during(|| {
+        for _ in 0..100 {
+            package.read_entry_into("stored.bin", &mut out).unwrap();
+        }
+    });
+    assert_eq!(allocations, 0);
+    assert_eq!(out, [0x5a]);
+}